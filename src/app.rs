@@ -1,181 +1,533 @@
+use crate::bank;
+use crate::classifier;
 use crate::database;
+use crate::discovery;
 use crate::event::{AppEvent, Event, EventHandler};
+use crate::export;
+use crate::history::{Change, Editable, History};
+use crate::scan::{self, OcrEntry, OcrType};
 use crate::settings;
 use float_cmp::{ApproxEq, F64Margin};
+use image::RgbImage;
+use notify::RecommendedWatcher;
 use ratatui::{
     DefaultTerminal,
-    crossterm::event::{KeyCode, KeyEvent},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    style::Color,
     widgets::ListState,
 };
-use regex::Regex;
-use rusty_tesseract::{Args, Image};
 use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
-use textdistance::str::damerau_levenshtein;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use tui_textarea::{CursorMove, TextArea};
 
 pub struct App<'a> {
+    archive_path: String,
+    background_ocr: bool,
+    pub batch_list: BatchList,
+    bon_history: History<NewBonList>,
+    pub bon_image: BonImage,
     pub bon_list: BonList,
     pub bon_summary: Vec<SummaryEntry>,
+    pub budget_list: BudgetList,
     pub category_list: CategoryList,
+    category_source: i64,
+    pub chart_list: ChartList,
+    pub classifier: classifier::Classifier,
     pub current_state: AppState,
     database: database::Database,
+    discovery: discovery::Discovery,
+    pub duplicate_bon: Option<database::Bon>,
     pub edit_field: TextArea<'a>,
     events: EventHandler,
+    export_path: String,
     pub import_list: FileList,
-    import_path: String,
+    _import_watcher: Option<RecommendedWatcher>,
     pub new_bon_list: NewBonList,
     ocr_blacklist: Vec<String>,
+    ocr_history: History<OcrList>,
+    pub ocr_cache: HashMap<String, Vec<OcrEntry>>,
     pub ocr_list: OcrList,
     pub ocr_file: String,
+    pub ocr_scanning: Vec<String>,
+    post_import: scan::PostImport,
+    pub price_list: PriceList,
+    price_threshold: f64,
+    pub reconcile_list: ReconcileList,
+    pub report_list: ReportList,
+    rule_is_regex: bool,
+    pub rule_list: RuleList,
+    rule_pattern: String,
     running: bool,
+    statement_format: bank::StatementFormat,
+    pub summary_level: usize,
+}
+
+pub struct BatchEntry {
+    pub draft: scan::BonDraft,
+    pub duplicate: Option<String>,
+    pub error: Option<String>,
+    pub file: String,
+    pub lines: Vec<OcrEntry>,
+}
+
+impl BatchEntry {
+    fn check_duplicate(&mut self, bons: &[database::Bon]) {
+        if self.duplicate.is_none()
+            && let Some(bon) = self.draft.find_duplicate(bons)
+        {
+            self.duplicate = Some(format!("same as bon {} from {}", bon.bon_id, bon.date));
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.error.is_none()
+            && self.duplicate.is_none()
+            && !self.draft.date.is_empty()
+            && !self.draft.items.is_empty()
+            && self.draft.price_matches()
+    }
+}
+
+#[derive(Default)]
+pub struct BonImage {
+    pub file: String,
+    pub image: Option<RgbImage>,
+    pub original_size: (u32, u32),
+    pub status: Option<String>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct BonHeader {
+    pub date: String,
+    pub price: f64,
+    pub store: String,
+}
+
+pub struct BatchList {
+    pub items: Vec<BatchEntry>,
+    pub pending: Vec<String>,
+    pub state: ListState,
 }
 
 pub struct BonList {
     pub items: Vec<database::Bon>,
     pub state: ListState,
+    pub status: Option<String>,
+}
+
+pub struct BudgetList {
+    pub items: Vec<database::Budget>,
+    pub month: String,
+    pub state: ListState,
 }
 
 pub struct CategoryList {
     pub items: Vec<database::Category>,
     pub state: ListState,
+    pub status: Option<String>,
+}
+
+pub struct ChartList {
+    pub products: ProductHistory,
+    pub reports: Vec<database::PeriodReport>,
+    pub state: ListState,
+    pub view: ChartView,
 }
 
 pub struct FileList {
-    pub items: Vec<String>,
+    pub items: Vec<discovery::ImportFile>,
     pub state: ListState,
+    pub warning: Option<(PathBuf, String)>,
 }
 
 pub struct NewBonList {
+    pub completions: Vec<String>,
     pub date: String,
     pub items: Vec<database::Entry>,
     pub price_calc: f64,
     pub price_eq: bool,
     pub price_ocr: f64,
     pub state: ListState,
+    pub store: String,
+    pub warnings: Vec<String>,
 }
 
-#[derive(Clone)]
-pub struct OcrEntry {
-    pub name: String,
-    pub ocr_type: OcrType,
+impl Editable for NewBonList {
+    type Header = BonHeader;
+    type Item = database::Entry;
+
+    fn header(&self) -> Self::Header {
+        BonHeader {
+            date: self.date.clone(),
+            price: self.price_ocr,
+            store: self.store.clone(),
+        }
+    }
+
+    fn items(&mut self) -> &mut Vec<Self::Item> {
+        &mut self.items
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        self.state.select(index);
+    }
+
+    fn set_header(&mut self, header: Self::Header) {
+        self.date = header.date;
+        self.price_ocr = header.price;
+        self.store = header.store;
+    }
 }
 
 pub struct OcrList {
     pub items: Vec<OcrEntry>,
     pub state: ListState,
+    pub status: Option<String>,
+}
+
+impl Editable for OcrList {
+    type Header = ();
+    type Item = OcrEntry;
+
+    fn header(&self) -> Self::Header {}
+
+    fn items(&mut self) -> &mut Vec<Self::Item> {
+        &mut self.items
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        self.state.select(index);
+    }
+
+    fn set_header(&mut self, _header: Self::Header) {}
+}
+
+pub struct PriceList {
+    pub alerts: Vec<i64>,
+    pub products: ProductHistory,
+}
+
+// product list with the price history of the selected product, shared by the charts and the
+// price history screen
+pub struct ProductHistory {
+    pub history: Vec<database::PricePoint>,
+    pub items: Vec<database::Product>,
+    pub months: usize,
+    pub state: ListState,
+}
+
+pub struct ReconcileList {
+    pub bon_state: ListState,
+    pub bons: Vec<database::Bon>,
+    pub focus: ReconcileFocus,
+    pub pair_state: ListState,
+    pub pairs: Vec<(database::Bon, database::Transaction)>,
+    pub status: Option<String>,
+    pub transaction_state: ListState,
+    pub transactions: Vec<database::Transaction>,
+}
+
+pub struct ReportList {
+    pub items: Vec<database::PeriodReport>,
+    pub period: database::ReportPeriod,
+    pub state: ListState,
+}
+
+pub struct RuleList {
+    pub items: Vec<database::Rule>,
+    pub state: ListState,
+    pub status: Option<String>,
+    pub updated: Option<usize>,
 }
 
 pub enum AppState {
+    AddEntry,
+    Batch,
     Blacklist,
+    BonImage,
+    Budgets,
     Category,
+    Charts,
     ConvertBon,
+    DeleteCategory,
+    DuplicateBon,
     EditBonPrice,
+    EditBudget,
     EditCategory,
+    EditCategoryColor,
+    EditDate,
     EditName,
+    EditOcrLine,
     EditPrice,
+    EditRule,
+    EditStore,
+    ExportCsv,
     Home,
     Import,
+    ImportStatement,
+    InsertOcrLine,
+    MergeCategory,
+    MoveCategory,
     OCR,
+    PriceHistory,
+    Reconcile,
+    RenameCategory,
+    Reports,
+    RuleCategory,
+    Rules,
+    SplitOcrLine,
+}
+
+pub enum ChartView {
+    Categories,
+    Product,
+    Spending,
 }
 
-#[derive(Clone)]
-pub enum OcrType {
-    Date,
-    Entry,
-    Sum,
+#[derive(PartialEq)]
+pub enum ReconcileFocus {
+    Bons,
+    Pairs,
+    Transactions,
 }
 
 pub struct SummaryEntry {
     pub category: String,
+    pub color: Option<String>,
     pub total: f64,
 }
 
 impl Default for App<'_> {
     fn default() -> Self {
-        let settings = settings::Settings::new();
-        let database_exists = settings.database_exists();
-        let database = database::Database::new(&settings.database_file);
-        if !database_exists {
-            database.create_database();
+        Self::with_settings(settings::Settings::new())
+    }
+}
+
+impl ChartList {
+    pub fn cutoff(&self) -> Option<String> {
+        database::month_cutoff(&self.reports.last()?.period, self.products.months)
+    }
+
+    pub fn reports_in_range(&self) -> Vec<&database::PeriodReport> {
+        let cutoff = self.cutoff().unwrap_or_default();
+        self.reports
+            .iter()
+            .filter(|report| report.period >= cutoff)
+            .collect()
+    }
+}
+
+impl ProductHistory {
+    fn load(&mut self, mut products: Vec<database::Product>) {
+        products.sort_by_key(|product| product.product.to_lowercase());
+        self.items = products;
+        if self.items.is_empty() {
+            self.state.select(None);
+        } else {
+            self.state.select_first();
         }
-        let bons = database.get_bons();
-        let blacklist = database.get_blacklist();
-        let processed = database.get_processed();
-        let import_list = read_ocr_files(&processed);
-        let category_list = database.get_categories();
+    }
+
+    fn new() -> Self {
         Self {
-            bon_list: BonList {
-                items: bons,
-                state: ListState::default(),
-            },
-            category_list: CategoryList {
-                items: category_list,
-                state: ListState::default(),
-            },
-            bon_summary: Vec::new(),
-            current_state: AppState::Home,
-            database,
-            edit_field: TextArea::default(),
-            events: EventHandler::new(),
-            import_list: FileList {
-                items: import_list,
-                state: ListState::default(),
-            },
-            import_path: settings.import_path(),
-            new_bon_list: NewBonList {
-                date: String::new(),
-                items: Vec::new(),
-                price_calc: 0.0,
-                price_eq: false,
-                price_ocr: 0.0,
-                state: ListState::default(),
-            },
-            ocr_blacklist: blacklist,
-            ocr_list: OcrList {
-                items: Vec::new(),
-                state: ListState::default(),
-            },
-            ocr_file: String::new(),
-            running: true,
+            history: Vec::new(),
+            items: Vec::new(),
+            months: 12,
+            state: ListState::default(),
         }
     }
+
+    fn update_history(&mut self, database: &database::Database) {
+        self.history = self
+            .state
+            .selected()
+            .and_then(|i| self.items.get(i))
+            .map(|product| database.get_price_history(product.product_id))
+            .unwrap_or_default();
+    }
+
+    pub fn window(&self) -> &[database::PricePoint] {
+        database::PriceStats::window(&self.history, self.months)
+    }
 }
 
 impl App<'_> {
+    fn apply_rules(&mut self) {
+        if matches!(self.current_state, AppState::Rules) {
+            self.rule_list.updated = Some(self.database.apply_rules());
+            self.bon_list.items = self.database.get_bons();
+            self.retrain_classifier();
+        }
+    }
+
+    fn approve_batch(&mut self, all: bool) {
+        if matches!(self.current_state, AppState::Batch) {
+            self.bon_list.status = None;
+            let selected = self.batch_list.state.selected();
+            let items = std::mem::take(&mut self.batch_list.items);
+            let rest = approve_batch_entries(items, selected, all, |entry| {
+                // two photos of the same bon can be in one batch
+                entry.check_duplicate(&self.database.get_bons());
+                if !entry.is_ready() {
+                    return false;
+                }
+                let bon_id = scan::import(&self.database, &entry.draft, &entry.file);
+                self.archive(bon_id, &entry.draft, &entry.file);
+                entry
+                    .draft
+                    .items
+                    .iter()
+                    .for_each(|item| self.classifier.train(&item.product, &item.category));
+                true
+            });
+            self.batch_list.items = rest;
+            if self.batch_list.items.is_empty() {
+                self.batch_list.state.select(None);
+            } else if selected.is_none_or(|i| i >= self.batch_list.items.len()) {
+                self.batch_list.state.select_last();
+            }
+            self.events.send(AppEvent::UpdateFromDatabase);
+        }
+    }
+
+    // a failed archive leaves the image where it is, the bon itself is already imported
+    fn archive(&mut self, bon_id: i64, draft: &scan::BonDraft, file: &str) {
+        // manual bons have no image
+        if file.is_empty() {
+            return;
+        }
+        if let Err(error) = scan::archive(
+            &self.database,
+            bon_id,
+            draft,
+            file,
+            self.post_import,
+            &self.archive_path,
+        ) {
+            self.bon_list.status = Some(format!("couldn't archive {}: {}", file, error));
+        }
+    }
+
+    fn background_scanned(&mut self, file: String, result: Result<Vec<OcrEntry>, String>) {
+        self.ocr_scanning.retain(|elem| *elem != file);
+        if let Ok(lines) = result {
+            self.ocr_cache.insert(file, lines);
+        }
+    }
+
+    fn batch_scanned(&mut self, file: String, result: Result<Vec<OcrEntry>, String>) {
+        self.batch_list.pending.retain(|elem| *elem != file);
+        let entry = match result {
+            Ok(mut lines) => {
+                scan::detect(&mut lines);
+                let mut entry = BatchEntry {
+                    draft: scan::convert(&self.database, &lines),
+                    duplicate: self.duplicate_warning(Path::new(&file)),
+                    error: None,
+                    file,
+                    lines,
+                };
+                entry.check_duplicate(&self.database.get_bons());
+                entry
+            }
+            Err(error) => BatchEntry {
+                draft: scan::BonDraft::default(),
+                duplicate: None,
+                error: Some(error),
+                file,
+                lines: Vec::new(),
+            },
+        };
+        self.batch_list.items.push(entry);
+        if self.batch_list.state.selected().is_none() {
+            self.batch_list.state.select_first();
+        }
+    }
+
+    fn bon_draft(&self) -> scan::BonDraft {
+        scan::BonDraft {
+            date: self.new_bon_list.date.clone(),
+            items: self.new_bon_list.items.clone(),
+            price: self.new_bon_list.price_ocr,
+            store: self.new_bon_list.store.clone(),
+        }
+    }
+
+    fn bon_image_loaded(&mut self, file: String, result: Result<(RgbImage, (u32, u32)), String>) {
+        if self.bon_image.file != file {
+            return;
+        }
+        match result {
+            Ok((image, original_size)) => {
+                self.bon_image.image = Some(image);
+                self.bon_image.original_size = original_size;
+                self.bon_image.status = None;
+            }
+            // formats the image crate can't decode still open in the system viewer
+            Err(_) if matches!(self.current_state, AppState::BonImage) => self.open_bon_image(),
+            Err(error) => self.bon_image.status = Some(error),
+        }
+    }
+
     fn calculate_summary(&mut self) {
         if matches!(self.current_state, AppState::Home) {
             if let Some(i) = self.bon_list.state.selected() {
                 let bon = &self.bon_list.items[i];
                 self.bon_summary.clear();
-                let mut summary_map: HashMap<String, f64> = HashMap::new();
+                let mut summary_map: HashMap<String, (f64, Option<String>)> = HashMap::new();
                 bon.entries.iter().for_each(|entry| {
+                    let (category, color) = database::Category::ancestor_at_level(
+                        &self.category_list.items,
+                        &entry.category,
+                        self.summary_level,
+                    )
+                    .map_or_else(
+                        || (entry.category.clone(), entry.color.clone()),
+                        |category| (category.category.clone(), category.color.clone()),
+                    );
                     summary_map
-                        .entry(entry.category.clone())
-                        .and_modify(|value| *value += entry.price)
-                        .or_insert(entry.price);
+                        .entry(category)
+                        .and_modify(|value| value.0 += entry.price)
+                        .or_insert((entry.price, color));
                 });
-                summary_map.iter().for_each(|(category, total)| {
+                summary_map.iter().for_each(|(category, (total, color))| {
                     self.bon_summary.push(SummaryEntry {
                         category: category.clone(),
+                        color: color.clone(),
                         total: *total,
                     });
                 });
                 let total_sum: f64 = self.bon_summary.iter().map(|e| e.total).sum();
                 self.bon_summary.push(SummaryEntry {
                     category: "total".to_string(),
+                    color: None,
                     total: total_sum,
                 });
             }
         } else if matches!(self.current_state, AppState::ConvertBon)
             | matches!(self.current_state, AppState::EditPrice)
         {
+            // while a price is edited the typed value already counts
+            let editing = if matches!(self.current_state, AppState::EditPrice) {
+                self.new_bon_list.state.selected().zip(
+                    self.edit_field
+                        .lines()
+                        .first()
+                        .and_then(|line| line.trim().replace(",", ".").parse::<f64>().ok()),
+                )
+            } else {
+                None
+            };
             self.new_bon_list.price_calc = self
                 .new_bon_list
                 .items
                 .iter()
-                .map(|entry| entry.price)
+                .enumerate()
+                .map(|(i, entry)| match editing {
+                    Some((j, price)) if i == j => price,
+                    _ => entry.price,
+                })
                 .sum();
             self.new_bon_list.price_eq = self.new_bon_list.price_ocr.approx_eq(
                 self.new_bon_list.price_calc,
@@ -184,62 +536,28 @@ impl App<'_> {
                     epsilon: 1.0,
                 },
             );
+            self.update_budget_warnings();
+        }
+    }
+
+    fn change_rule_priority(&mut self, change: i64) {
+        if let Some(i) = self.rule_list.state.selected()
+            && let Some(rule) = self.rule_list.items.get(i)
+        {
+            self.database
+                .set_rule_priority(rule.rule_id, rule.priority + change);
+            self.events.send(AppEvent::UpdateFromDatabase);
         }
     }
 
     fn convert_to_bon(&mut self) {
-        self.new_bon_list.date = String::new();
-        self.new_bon_list.items.clear();
+        let draft = scan::convert(&self.database, &self.ocr_list.items);
+        self.bon_history.clear();
+        self.new_bon_list.date = draft.date;
+        self.new_bon_list.items = draft.items;
         self.new_bon_list.price_calc = 0.0;
-        self.new_bon_list.price_ocr = 0.0;
-
-        self.ocr_list
-            .items
-            .iter()
-            .for_each(|elem| match elem.ocr_type {
-                OcrType::Date => {
-                    if let Some(date) = Self::extract_date(&elem.name) {
-                        self.new_bon_list.date = date;
-                    }
-                }
-                OcrType::Entry => {
-                    if let Some(name) = Self::extract_name(&elem.name) {
-                        if let Some(price) = Self::extract_price(&elem.name) {
-                            let db_products = self.database.get_products();
-                            let db_product = db_products
-                                .iter()
-                                .min_by_key(|elem| damerau_levenshtein(&name, &elem.product));
-                            let distance = if let Some(product) = &db_product {
-                                damerau_levenshtein(&name, &product.product)
-                            } else {
-                                usize::MAX
-                            };
-                            let mut category = String::new();
-                            let mut product = name;
-                            if distance < 4 {
-                                let db_product = db_product.unwrap();
-                                product = db_product.product.clone();
-                                let db_categories = self.database.get_categories();
-                                category = db_categories
-                                    .iter()
-                                    .find(|category| category.category_id == db_product.category_id)
-                                    .map(|category| category.category.clone())
-                                    .unwrap_or_else(|| "".to_string());
-                            }
-                            self.new_bon_list.items.push(database::Entry {
-                                category,
-                                product,
-                                price,
-                            });
-                        }
-                    }
-                }
-                OcrType::Sum => {
-                    if let Some(sum) = Self::extract_price(&elem.name) {
-                        self.new_bon_list.price_ocr = sum;
-                    }
-                }
-            });
+        self.new_bon_list.price_ocr = draft.price;
+        self.new_bon_list.store = draft.store;
 
         if !self.new_bon_list.items.is_empty() {
             self.new_bon_list.state.select_first();
@@ -248,20 +566,47 @@ impl App<'_> {
         self.events.send(AppEvent::CalculateSummary);
     }
 
-    fn extract_date(line: &str) -> Option<String> {
-        let re = Regex::new(r"\d{2}[\.,]\d{2}[\.,]\d{4}").expect("Could not compile regex");
-        re.find(line).map(|m| m.as_str().to_string())
+    // a renamed or copied bon isn't filtered from the import list, but its content gives it away
+    fn duplicate_warning(&self, file: &Path) -> Option<String> {
+        scan::identical_file(file, &self.database.get_processed_files())
+    }
+
+    fn edit_bon_entry(&mut self, edit: impl FnOnce(&mut database::Entry)) {
+        if let Some(i) = self.new_bon_list.state.selected()
+            && let Some(entry) = self.new_bon_list.items.get(i).cloned()
+        {
+            let mut edited = entry.clone();
+            edit(&mut edited);
+            if edited != entry {
+                self.bon_history.record(
+                    &mut self.new_bon_list,
+                    vec![Change::Replace(i, entry, edited)],
+                );
+            }
+        }
     }
 
-    fn extract_name(line: &str) -> Option<String> {
-        let name = line.rsplit_once(' ');
-        name.map(|name| name.0.to_string())
+    fn edit_bon_header(&mut self, edit: impl FnOnce(&mut BonHeader)) {
+        let header = self.new_bon_list.header();
+        let mut edited = header.clone();
+        edit(&mut edited);
+        if edited != header {
+            self.bon_history
+                .record(&mut self.new_bon_list, vec![Change::Header(header, edited)]);
+        }
     }
 
-    fn extract_price(line: &str) -> Option<f64> {
-        let re = Regex::new(r"(\d+[.,]\d+)").expect("Could not compile regex");
-        re.find(line)
-            .and_then(|m| m.as_str().replace(',', ".").parse::<f64>().ok())
+    fn edit_ocr_line(&mut self, edit: impl FnOnce(&mut OcrEntry)) {
+        if let Some(i) = self.ocr_list.state.selected()
+            && let Some(line) = self.ocr_list.items.get(i).cloned()
+        {
+            let mut edited = line.clone();
+            edit(&mut edited);
+            if edited != line {
+                self.ocr_history
+                    .record(&mut self.ocr_list, vec![Change::Replace(i, line, edited)]);
+            }
+        }
     }
 
     pub fn handle_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
@@ -276,15 +621,121 @@ impl App<'_> {
                 KeyCode::Esc => self.events.send(AppEvent::GoOcrState),
                 _ => _ = self.edit_field.input(key_event),
             }
+        } else if matches!(self.current_state, AppState::EditBudget) {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let limit = self
+                        .edit_field
+                        .lines()
+                        .first()
+                        .and_then(|line| line.trim().replace(",", ".").parse::<f64>().ok())
+                        .unwrap_or(0.0);
+                    if let Some(i) = self.budget_list.state.selected()
+                        && let Some(budget) = self.budget_list.items.get(i)
+                    {
+                        if limit > 0.0 {
+                            self.database.set_budget(budget.category_id, limit);
+                        } else {
+                            self.database.delete_budget(budget.category_id);
+                        }
+                    }
+                    self.events.send(AppEvent::GoBudgetsState);
+                    self.events.send(AppEvent::UpdateFromDatabase);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoBudgetsState),
+                _ => _ = self.edit_field.input(key_event),
+            }
+        } else if matches!(self.current_state, AppState::ImportStatement) {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let file = self.edit_field.lines()[0].trim().to_string();
+                    self.reconcile_list.status = Some(
+                        match bank::import_statement(
+                            &self.database,
+                            Path::new(&file),
+                            &self.statement_format,
+                        ) {
+                            Ok(imported) => format!(
+                                "imported {} transactions, paired {}",
+                                imported,
+                                bank::pair(&self.database)
+                            ),
+                            Err(error) => format!("couldn't import {}: {}", file, error),
+                        },
+                    );
+                    self.events.send(AppEvent::GoReconcileState);
+                    self.events.send(AppEvent::UpdateFromDatabase);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoReconcileState),
+                _ => _ = self.edit_field.input(key_event),
+            }
+        } else if matches!(self.current_state, AppState::DuplicateBon) {
+            match key_event.code {
+                KeyCode::Enter | KeyCode::Char('y') => self.events.send(AppEvent::ImportBon),
+                KeyCode::Esc | KeyCode::Char('n') => {
+                    self.duplicate_bon = None;
+                    self.events.send(AppEvent::GoConvertBonState);
+                }
+                _ => {}
+            }
+        } else if matches!(self.current_state, AppState::ExportCsv) {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let filter = export::ExportFilter::parse(self.edit_field.lines()[0].as_str());
+                    self.bon_list.status = Some(
+                        match export::write_csv(
+                            &self.database,
+                            Path::new(&self.export_path),
+                            &filter,
+                        ) {
+                            Ok(()) => format!("exported to {}", self.export_path),
+                            Err(error) => {
+                                format!("couldn't export to {}: {}", self.export_path, error)
+                            }
+                        },
+                    );
+                    self.events.send(AppEvent::GoHomeState);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoHomeState),
+                _ => _ = self.edit_field.input(key_event),
+            }
+        } else if matches!(self.current_state, AppState::EditRule) {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let pattern = self.edit_field.lines()[0].trim();
+                    // a pattern between slashes is a regex
+                    (self.rule_pattern, self.rule_is_regex) = match pattern
+                        .strip_prefix('/')
+                        .and_then(|pattern| pattern.strip_suffix('/'))
+                    {
+                        Some(regex) if !regex.is_empty() => (regex.to_string(), true),
+                        _ => (pattern.to_string(), false),
+                    };
+                    if self.rule_pattern.is_empty() {
+                        self.events.send(AppEvent::GoRulesState);
+                    } else if let Err(error) =
+                        database::Rule::check_pattern(&self.rule_pattern, self.rule_is_regex)
+                    {
+                        self.rule_list.status = error.message;
+                    } else {
+                        self.rule_list.status = None;
+                        self.events.send(AppEvent::GoRuleCategoryState);
+                    }
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoRulesState),
+                _ => _ = self.edit_field.input(key_event),
+            }
         } else if matches!(self.current_state, AppState::EditBonPrice)
+            | matches!(self.current_state, AppState::EditDate)
             | matches!(self.current_state, AppState::EditName)
             | matches!(self.current_state, AppState::EditPrice)
+            | matches!(self.current_state, AppState::EditStore)
         {
             match key_event.code {
                 KeyCode::Enter => {
                     match self.current_state {
                         AppState::EditBonPrice => {
-                            self.new_bon_list.price_ocr = self
+                            let price = self
                                 .edit_field
                                 .lines()
                                 .first()
@@ -293,74 +744,306 @@ impl App<'_> {
                                     repl.parse::<f64>().ok()
                                 })
                                 .unwrap_or(0.0);
+                            self.edit_bon_header(|header| header.price = price);
                         }
                         AppState::EditCategory => {
-                            if let Some(i) = self.new_bon_list.state.selected() {
-                                if let Some(entry) = self.new_bon_list.items.get_mut(i) {
-                                    entry.category = self.edit_field.lines()[0].clone();
-                                }
+                            let category = self.edit_field.lines()[0].clone();
+                            self.edit_bon_entry(|entry| entry.category = category);
+                        }
+                        AppState::EditDate => {
+                            if let Some(date) = scan::extract_date(&self.edit_field.lines()[0]) {
+                                self.edit_bon_header(|header| header.date = date);
                             }
                         }
                         AppState::EditName => {
-                            if let Some(i) = self.new_bon_list.state.selected() {
-                                if let Some(entry) = self.new_bon_list.items.get_mut(i) {
-                                    entry.product = self.edit_field.lines()[0].clone();
-                                }
-                            }
+                            let product = self.edit_field.lines()[0].clone();
+                            self.edit_bon_entry(|entry| entry.product = product);
                         }
                         AppState::EditPrice => {
-                            if let Some(i) = self.new_bon_list.state.selected() {
-                                if let Some(entry) = self.new_bon_list.items.get_mut(i) {
-                                    entry.price = self
-                                        .edit_field
-                                        .lines()
-                                        .first()
-                                        .and_then(|line| {
-                                            let repl = line.replace(",", ".");
-                                            repl.parse::<f64>().ok()
-                                        })
-                                        .unwrap_or(0.0);
-                                }
-                            }
+                            let price = self
+                                .edit_field
+                                .lines()
+                                .first()
+                                .and_then(|line| {
+                                    let repl = line.replace(",", ".");
+                                    repl.parse::<f64>().ok()
+                                })
+                                .unwrap_or(0.0);
+                            self.edit_bon_entry(|entry| entry.price = price);
+                        }
+                        AppState::EditStore => {
+                            let store = self.edit_field.lines()[0].trim().to_string();
+                            self.edit_bon_header(|header| header.store = store);
                         }
                         _ => {}
                     }
                     self.events.send(AppEvent::GoConvertBonState);
                     self.events.send(AppEvent::CalculateSummary);
                 }
-                KeyCode::Esc => self.events.send(AppEvent::GoConvertBonState),
-                _ => _ = self.edit_field.input(key_event),
-            }
-        } else if matches!(self.current_state, AppState::EditCategory) {
-            match key_event.code {
-                KeyCode::Enter => {
-                    let category = self.edit_field.lines()[0].as_str();
-                    let category_exists = self
-                        .category_list
-                        .items
-                        .iter()
-                        .any(|elem| elem.category == category);
-                    if !category_exists {
-                        self.database
-                            .create_category(self.edit_field.lines()[0].as_str());
+                KeyCode::Esc => {
+                    self.events.send(AppEvent::GoConvertBonState);
+                    self.events.send(AppEvent::CalculateSummary);
+                }
+                _ => {
+                    self.edit_field.input(key_event);
+                    if matches!(self.current_state, AppState::EditPrice) {
+                        self.events.send(AppEvent::CalculateSummary);
                     }
-                    self.events.send(AppEvent::GoCategoryState);
-                    self.events.send(AppEvent::UpdateFromDatabase);
                 }
-                KeyCode::Esc => self.events.send(AppEvent::GoCategoryState),
-                _ => _ = self.edit_field.input(key_event),
             }
-        } else {
+        } else if matches!(self.current_state, AppState::AddEntry) {
             match key_event.code {
-                KeyCode::Char('a') => {
-                    if matches!(self.current_state, AppState::Category) {
+                KeyCode::Enter => {
+                    let product = self.edit_field.lines()[0].trim().to_string();
+                    self.events.send(AppEvent::GoConvertBonState);
+                    if !product.is_empty() {
+                        let entry = scan::manual_entry(&self.database, &product, 0.0);
+                        let i = self
+                            .new_bon_list
+                            .state
+                            .selected()
+                            .map_or(self.new_bon_list.items.len(), |i| i + 1);
+                        self.bon_history
+                            .record(&mut self.new_bon_list, vec![Change::Insert(i, entry)]);
+                        // the price is asked for right away
                         self.edit_field.move_cursor(CursorMove::End);
                         self.edit_field.delete_line_by_head();
-                        self.events.send(AppEvent::GoEditCategoryState);
+                        self.events.send(AppEvent::GoEditPriceState);
                     }
+                    self.events.send(AppEvent::CalculateSummary);
                 }
-                KeyCode::Char('b') => {
-                    if matches!(self.current_state, AppState::OCR) {
+                KeyCode::Esc => self.events.send(AppEvent::GoConvertBonState),
+                KeyCode::Tab => {
+                    if let Some(product) = self.new_bon_list.completions.first().cloned() {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field.insert_str(product);
+                        self.new_bon_list.completions = scan::complete_product(
+                            &self.database.get_products(),
+                            &self.edit_field.lines()[0],
+                        );
+                    }
+                }
+                _ => {
+                    self.edit_field.input(key_event);
+                    self.new_bon_list.completions = scan::complete_product(
+                        &self.database.get_products(),
+                        &self.edit_field.lines()[0],
+                    );
+                }
+            }
+        } else if matches!(self.current_state, AppState::EditOcrLine)
+            | matches!(self.current_state, AppState::InsertOcrLine)
+            | matches!(self.current_state, AppState::SplitOcrLine)
+        {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let line = self.edit_field.lines()[0].trim().to_string();
+                    let selected = self.ocr_list.state.selected();
+                    match self.current_state {
+                        AppState::EditOcrLine if !line.is_empty() => {
+                            self.edit_ocr_line(|entry| entry.name = line);
+                        }
+                        AppState::InsertOcrLine if !line.is_empty() => {
+                            let i = selected.map_or(0, |i| i + 1);
+                            let entry = OcrEntry {
+                                name: line,
+                                ocr_type: OcrType::Entry,
+                                region: None,
+                            };
+                            self.ocr_history
+                                .record(&mut self.ocr_list, vec![Change::Insert(i, entry)]);
+                        }
+                        AppState::SplitOcrLine => {
+                            if let Some(i) = selected
+                                && let Some(entry) = self.ocr_list.items.get(i).cloned()
+                            {
+                                // split a copy, the history applies the result
+                                let mut lines = vec![entry.clone()];
+                                let (_, column) = self.edit_field.cursor();
+                                if scan::split_line(
+                                    &mut lines,
+                                    0,
+                                    &self.edit_field.lines()[0],
+                                    column,
+                                ) {
+                                    let second = lines.pop().expect("Couldn't split line");
+                                    let first = lines.pop().expect("Couldn't split line");
+                                    self.ocr_history.record(
+                                        &mut self.ocr_list,
+                                        vec![
+                                            Change::Replace(i, entry, first),
+                                            Change::Insert(i + 1, second),
+                                        ],
+                                    );
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.events.send(AppEvent::GoOcrState);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoOcrState),
+                _ => _ = self.edit_field.input(key_event),
+            }
+        } else if matches!(self.current_state, AppState::EditCategory) {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let category = self.edit_field.lines()[0].as_str();
+                    let category_exists = self
+                        .category_list
+                        .items
+                        .iter()
+                        .any(|elem| elem.category == category);
+                    if !category_exists {
+                        self.database
+                            .create_category(self.edit_field.lines()[0].as_str());
+                    }
+                    self.events.send(AppEvent::GoCategoryState);
+                    self.events.send(AppEvent::UpdateFromDatabase);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoCategoryState),
+                _ => _ = self.edit_field.input(key_event),
+            }
+        } else if matches!(self.current_state, AppState::EditCategoryColor)
+            | matches!(self.current_state, AppState::RenameCategory)
+        {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let input = self.edit_field.lines()[0].trim().to_string();
+                    if let Some(i) = self.category_list.state.selected()
+                        && let Some(category) = self.category_list.items.get(i)
+                    {
+                        if matches!(self.current_state, AppState::RenameCategory) {
+                            self.category_list.status = None;
+                            let existing = self
+                                .category_list
+                                .items
+                                .iter()
+                                .position(|elem| elem.category == input);
+                            if let Some(j) = existing
+                                && self.category_list.items[j].category_id != category.category_id
+                            {
+                                // renaming onto another category means merging into it
+                                self.category_source = category.category_id;
+                                self.category_list.state.select(Some(j));
+                                self.category_list.status = Some(format!(
+                                    "{} already exists, Enter merges {} into it",
+                                    input, category.category
+                                ));
+                                self.events.send(AppEvent::GoMergeCategoryState);
+                                return Ok(());
+                            } else if !input.is_empty() {
+                                match self
+                                    .database
+                                    .rename_category(category.category_id, input.as_str())
+                                {
+                                    Ok(()) => {
+                                        self.new_bon_list
+                                            .items
+                                            .iter_mut()
+                                            .filter(|item| item.category == category.category)
+                                            .for_each(|item| item.category = input.clone());
+                                        self.retrain_classifier();
+                                    }
+                                    Err(error) => {
+                                        self.category_list.status =
+                                            Some(format!("Couldn't rename to {}: {}", input, error))
+                                    }
+                                }
+                            }
+                        } else if input.is_empty() {
+                            self.database.set_category_color(category.category_id, None);
+                        } else if Color::from_str(&input).is_ok() {
+                            self.database
+                                .set_category_color(category.category_id, Some(input.as_str()));
+                        }
+                    }
+                    self.events.send(AppEvent::GoCategoryState);
+                    self.events.send(AppEvent::UpdateFromDatabase);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoCategoryState),
+                _ => _ = self.edit_field.input(key_event),
+            }
+        } else if matches!(self.current_state, AppState::DeleteCategory)
+            | matches!(self.current_state, AppState::MergeCategory)
+            | matches!(self.current_state, AppState::MoveCategory)
+        {
+            match key_event.code {
+                KeyCode::Char('j') => self.events.send(AppEvent::NextItem),
+                KeyCode::Char('k') => self.events.send(AppEvent::PreviousItem),
+                KeyCode::Char('q') => self.events.send(AppEvent::Quit),
+                KeyCode::Enter => {
+                    if let Some(i) = self.category_list.state.selected()
+                        && let Some(target) = self.category_list.items.get(i)
+                    {
+                        let target_id = target.category_id;
+                        if matches!(self.current_state, AppState::MoveCategory) {
+                            self.move_category(target_id);
+                        } else if target_id != self.category_source {
+                            self.reassign_category(target_id);
+                        }
+                    }
+                    self.events.send(AppEvent::GoCategoryState);
+                    self.events.send(AppEvent::UpdateFromDatabase);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoCategoryState),
+                _ => {}
+            }
+        } else if matches!(self.current_state, AppState::RuleCategory) {
+            match key_event.code {
+                KeyCode::Char('j') => self.events.send(AppEvent::NextItem),
+                KeyCode::Char('k') => self.events.send(AppEvent::PreviousItem),
+                KeyCode::Char('q') => self.events.send(AppEvent::Quit),
+                KeyCode::Enter => {
+                    if let Some(i) = self.category_list.state.selected()
+                        && let Some(category) = self.category_list.items.get(i)
+                        && let Err(error) = self.database.create_rule(
+                            self.rule_pattern.as_str(),
+                            self.rule_is_regex,
+                            category.category_id,
+                            0,
+                        )
+                    {
+                        self.rule_list.status = Some(format!("Couldn't add rule: {}", error));
+                    }
+                    self.events.send(AppEvent::GoRulesState);
+                    self.events.send(AppEvent::UpdateFromDatabase);
+                }
+                KeyCode::Esc => self.events.send(AppEvent::GoRulesState),
+                _ => {}
+            }
+        } else {
+            match key_event.code {
+                KeyCode::Char('a') => {
+                    if matches!(self.current_state, AppState::Home) {
+                        self.events.send(AppEvent::GoReconcileState);
+                    } else if matches!(self.current_state, AppState::Batch) {
+                        self.approve_batch(true);
+                    } else if matches!(self.current_state, AppState::Reconcile) {
+                        let paired = bank::pair(&self.database);
+                        self.reconcile_list.status = Some(format!("paired {}", paired));
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    } else if matches!(self.current_state, AppState::OCR) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.events.send(AppEvent::GoInsertOcrLineState);
+                    } else if matches!(self.current_state, AppState::Category) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.events.send(AppEvent::GoEditCategoryState);
+                    } else if matches!(self.current_state, AppState::Rules) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.events.send(AppEvent::GoEditRuleState);
+                    } else if matches!(self.current_state, AppState::ConvertBon) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.events.send(AppEvent::GoAddEntryState);
+                    }
+                }
+                KeyCode::Char('b') => {
+                    if matches!(self.current_state, AppState::OCR) {
                         self.edit_field.move_cursor(CursorMove::End);
                         self.edit_field.delete_line_by_head();
                         if let Some(i) = self.ocr_list.state.selected() {
@@ -368,80 +1051,421 @@ impl App<'_> {
                                 .insert_str(self.ocr_list.items[i].name.as_str());
                         }
                         self.events.send(AppEvent::GoBlacklistState);
+                    } else if matches!(self.current_state, AppState::Home) {
+                        self.events.send(AppEvent::GoBudgetsState);
+                    } else if matches!(self.current_state, AppState::Import) {
+                        self.events.send(AppEvent::StartBatch);
                     }
                 }
                 KeyCode::Char('c') => self.events.send(AppEvent::GoCategoryState),
-                KeyCode::Char('d') => self.events.send(AppEvent::OcrMarkDate),
+                KeyCode::Char('d') => {
+                    if matches!(self.current_state, AppState::ConvertBon) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field.insert_str(self.new_bon_list.date.as_str());
+                        self.events.send(AppEvent::GoEditDateState);
+                    } else {
+                        self.events.send(AppEvent::OcrMarkDate);
+                    }
+                }
+                KeyCode::Char('e') => {
+                    if matches!(self.current_state, AppState::ConvertBon) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field.insert_str(self.new_bon_list.store.as_str());
+                        self.events.send(AppEvent::GoEditStoreState);
+                    } else if matches!(self.current_state, AppState::Home) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.events.send(AppEvent::GoExportCsvState);
+                    } else if matches!(self.current_state, AppState::OCR)
+                        && let Some(i) = self.ocr_list.state.selected()
+                        && let Some(entry) = self.ocr_list.items.get(i)
+                    {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field.insert_str(entry.name.as_str());
+                        self.events.send(AppEvent::GoEditOcrLineState);
+                    }
+                }
+                KeyCode::Char('g') => self.events.send(AppEvent::GoChartsState),
                 KeyCode::Char('h') => self.events.send(AppEvent::HideItem),
-                KeyCode::Char('i') => self.events.send(AppEvent::GoImportState),
+                KeyCode::Char('i') => {
+                    if matches!(self.current_state, AppState::Reconcile) {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.events.send(AppEvent::GoImportStatementState);
+                    } else {
+                        self.events.send(AppEvent::GoImportState);
+                    }
+                }
                 KeyCode::Char('j') => self.events.send(AppEvent::NextItem),
                 KeyCode::Char('k') => self.events.send(AppEvent::PreviousItem),
+                KeyCode::Char('l') => {
+                    if matches!(self.current_state, AppState::Category)
+                        && let Some(i) = self.category_list.state.selected()
+                    {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        if let Some(color) = &self.category_list.items[i].color {
+                            self.edit_field.insert_str(color);
+                        }
+                        self.events.send(AppEvent::GoEditCategoryColorState);
+                    }
+                }
+                KeyCode::Char('m') => {
+                    if matches!(self.current_state, AppState::Category)
+                        && let Some(i) = self.category_list.state.selected()
+                    {
+                        self.category_source = self.category_list.items[i].category_id;
+                        self.events.send(AppEvent::GoMergeCategoryState);
+                    } else if matches!(self.current_state, AppState::Home) {
+                        self.events.send(AppEvent::GoReportsState);
+                    } else if matches!(self.current_state, AppState::OCR)
+                        && let Some(i) = self.ocr_list.state.selected()
+                        && let Some(lines) = self.ocr_list.items.get(i..i + 2).map(<[_]>::to_vec)
+                    {
+                        // merge a copy, the history applies the result
+                        let mut merged = lines.clone();
+                        scan::merge_lines(&mut merged, 0);
+                        self.ocr_history.record(
+                            &mut self.ocr_list,
+                            vec![
+                                Change::Replace(i, lines[0].clone(), merged.remove(0)),
+                                Change::Remove(i + 1, lines[1].clone()),
+                            ],
+                        );
+                    }
+                }
                 KeyCode::Char('n') => {
-                    self.edit_field.move_cursor(CursorMove::End);
-                    self.edit_field.delete_line_by_head();
-                    if let Some(i) = self.new_bon_list.state.selected() {
-                        self.edit_field
-                            .insert_str(self.new_bon_list.items[i].product.as_str());
+                    if matches!(self.current_state, AppState::Home) {
+                        self.manual_bon();
+                    } else {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        if let Some(i) = self.new_bon_list.state.selected() {
+                            self.edit_field
+                                .insert_str(self.new_bon_list.items[i].product.as_str());
+                        }
+                        self.events.send(AppEvent::GoEditNameState);
                     }
-                    self.events.send(AppEvent::GoEditNameState);
                 }
                 KeyCode::Char('o') => {
-                    self.edit_field.move_cursor(CursorMove::End);
-                    self.edit_field.delete_line_by_head();
-                    self.edit_field
-                        .insert_str(self.new_bon_list.price_ocr.to_string());
-                    self.events.send(AppEvent::GoEditBonPriceState);
+                    if matches!(self.current_state, AppState::BonImage) {
+                        self.open_bon_image();
+                    } else {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field
+                            .insert_str(self.new_bon_list.price_ocr.to_string());
+                        self.events.send(AppEvent::GoEditBonPriceState);
+                    }
                 }
                 KeyCode::Char('p') => {
-                    self.edit_field.move_cursor(CursorMove::End);
-                    self.edit_field.delete_line_by_head();
-                    if let Some(i) = self.new_bon_list.state.selected() {
-                        self.edit_field
-                            .insert_str(self.new_bon_list.items[i].price.to_string());
+                    if matches!(self.current_state, AppState::Home) {
+                        self.events.send(AppEvent::GoPriceHistoryState);
+                    } else {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        if let Some(i) = self.new_bon_list.state.selected() {
+                            self.edit_field
+                                .insert_str(self.new_bon_list.items[i].price.to_string());
+                        }
+                        self.events.send(AppEvent::GoEditPriceState);
                     }
-                    self.events.send(AppEvent::GoEditPriceState);
                 }
                 KeyCode::Char('q') => self.events.send(AppEvent::Quit),
+                KeyCode::Char('+') => {
+                    if matches!(self.current_state, AppState::Charts) {
+                        self.chart_list.products.months =
+                            (self.chart_list.products.months + 1).min(120);
+                    } else if matches!(self.current_state, AppState::PriceHistory) {
+                        self.price_list.products.months =
+                            (self.price_list.products.months + 1).min(120);
+                    } else if matches!(self.current_state, AppState::Rules) {
+                        self.change_rule_priority(1);
+                    } else if matches!(self.current_state, AppState::Home) {
+                        let max_level = self
+                            .category_list
+                            .items
+                            .iter()
+                            .map(|category| category.depth)
+                            .max()
+                            .unwrap_or(0);
+                        if self.summary_level < max_level {
+                            self.summary_level += 1;
+                            self.events.send(AppEvent::CalculateSummary);
+                        }
+                    }
+                }
+                KeyCode::Char('-') => {
+                    if matches!(self.current_state, AppState::Charts) {
+                        self.chart_list.products.months =
+                            self.chart_list.products.months.saturating_sub(1).max(1);
+                    } else if matches!(self.current_state, AppState::PriceHistory) {
+                        self.price_list.products.months =
+                            self.price_list.products.months.saturating_sub(1).max(1);
+                    } else if matches!(self.current_state, AppState::Rules) {
+                        self.change_rule_priority(-1);
+                    } else if matches!(self.current_state, AppState::Home) {
+                        self.summary_level = self.summary_level.saturating_sub(1);
+                        self.events.send(AppEvent::CalculateSummary);
+                    }
+                }
+                KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.redo();
+                }
+                KeyCode::Char('r') => {
+                    if matches!(self.current_state, AppState::Category)
+                        && let Some(i) = self.category_list.state.selected()
+                    {
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field
+                            .insert_str(self.category_list.items[i].category.as_str());
+                        self.events.send(AppEvent::GoRenameCategoryState);
+                    } else if matches!(self.current_state, AppState::Home) {
+                        self.events.send(AppEvent::GoRulesState);
+                    } else if matches!(self.current_state, AppState::Rules) {
+                        self.events.send(AppEvent::ApplyRules);
+                    }
+                }
                 KeyCode::Char('s') => self.events.send(AppEvent::OcrMarkSum),
+                KeyCode::Char('t') => {
+                    if matches!(self.current_state, AppState::Charts) {
+                        self.chart_list.view = match self.chart_list.view {
+                            ChartView::Categories => ChartView::Product,
+                            ChartView::Product => ChartView::Spending,
+                            ChartView::Spending => ChartView::Categories,
+                        };
+                    } else if matches!(self.current_state, AppState::Reports) {
+                        self.report_list.period = match self.report_list.period {
+                            database::ReportPeriod::Month => database::ReportPeriod::Year,
+                            database::ReportPeriod::Year => database::ReportPeriod::Month,
+                        };
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    } else if matches!(self.current_state, AppState::Rules)
+                        && let Some(i) = self.rule_list.state.selected()
+                        && let Some(rule) = self.rule_list.items.get(i)
+                    {
+                        self.rule_list.status = self
+                            .database
+                            .set_rule_regex(rule.rule_id, !rule.is_regex)
+                            .err()
+                            .and_then(|error| error.message);
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    }
+                }
+                KeyCode::Char('u') => {
+                    if matches!(self.current_state, AppState::Category)
+                        && let Some(i) = self.category_list.state.selected()
+                    {
+                        self.category_source = self.category_list.items[i].category_id;
+                        self.events.send(AppEvent::GoMoveCategoryState);
+                    } else if matches!(self.current_state, AppState::ConvertBon)
+                        | matches!(self.current_state, AppState::OCR)
+                    {
+                        self.undo();
+                    }
+                }
+                KeyCode::Char('v') => self.events.send(AppEvent::GoBonImageState),
+                KeyCode::Char('w') => {
+                    if matches!(self.current_state, AppState::OCR)
+                        && let Some(i) = self.ocr_list.state.selected()
+                        && let Some(entry) = self.ocr_list.items.get(i)
+                    {
+                        // the cursor starts at the end, the line is split where it is moved to
+                        self.edit_field.move_cursor(CursorMove::End);
+                        self.edit_field.delete_line_by_head();
+                        self.edit_field.insert_str(entry.name.as_str());
+                        self.events.send(AppEvent::GoSplitOcrLineState);
+                    }
+                }
                 KeyCode::Char('x') => {
                     if matches!(self.current_state, AppState::OCR) {
-                        if let Some(i) = self.ocr_list.state.selected() {
-                            self.ocr_list.items.remove(i);
+                        if let Some(i) = self.ocr_list.state.selected()
+                            && let Some(entry) = self.ocr_list.items.get(i).cloned()
+                        {
+                            self.ocr_history
+                                .record(&mut self.ocr_list, vec![Change::Remove(i, entry)]);
                         }
                     } else if matches!(self.current_state, AppState::ConvertBon) {
-                        if let Some(i) = self.new_bon_list.state.selected() {
-                            self.new_bon_list.items.remove(i);
+                        if let Some(i) = self.new_bon_list.state.selected()
+                            && let Some(entry) = self.new_bon_list.items.get(i).cloned()
+                        {
+                            self.bon_history
+                                .record(&mut self.new_bon_list, vec![Change::Remove(i, entry)]);
                         }
                         self.events.send(AppEvent::CalculateSummary);
+                    } else if matches!(self.current_state, AppState::Rules) {
+                        if let Some(i) = self.rule_list.state.selected()
+                            && let Some(rule) = self.rule_list.items.get(i)
+                        {
+                            self.database.delete_rule(rule.rule_id);
+                        }
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    } else if matches!(self.current_state, AppState::Budgets) {
+                        if let Some(i) = self.budget_list.state.selected()
+                            && let Some(budget) = self.budget_list.items.get(i)
+                        {
+                            self.database.delete_budget(budget.category_id);
+                        }
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    } else if matches!(self.current_state, AppState::Reconcile) {
+                        if self.reconcile_list.focus == ReconcileFocus::Pairs
+                            && let Some(i) = self.reconcile_list.pair_state.selected()
+                            && let Some((_, transaction)) = self.reconcile_list.pairs.get(i)
+                        {
+                            self.database
+                                .pair_transaction(transaction.transaction_id, None);
+                        }
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    } else if matches!(self.current_state, AppState::Category)
+                        && let Some(i) = self.category_list.state.selected()
+                    {
+                        let category_id = self.category_list.items[i].category_id;
+                        let in_use = self
+                            .database
+                            .get_products()
+                            .iter()
+                            .any(|product| product.category_id == category_id);
+                        if in_use {
+                            self.category_source = category_id;
+                            self.events.send(AppEvent::GoDeleteCategoryState);
+                        } else {
+                            self.database.delete_empty_category(category_id);
+                            self.events.send(AppEvent::UpdateFromDatabase);
+                        }
                     }
                 }
+                KeyCode::Char('y') => {
+                    if matches!(self.current_state, AppState::ConvertBon)
+                        && let Some(i) = self.new_bon_list.state.selected()
+                        && let Some(entry) = self.new_bon_list.items.get(i)
+                        && entry.category.is_empty()
+                        && let Some(suggestion) = self.classifier.suggest(&entry.product)
+                        // never bring back a category that no longer exists
+                        && let Some(category) = self
+                            .category_list
+                            .items
+                            .iter()
+                            .find(|category| category.category == suggestion.category)
+                    {
+                        let color = category.color.clone();
+                        self.edit_bon_entry(|entry| {
+                            entry.category = suggestion.category;
+                            entry.color = color;
+                        });
+                    } else if matches!(self.current_state, AppState::Batch) {
+                        self.approve_batch(false);
+                    }
+                }
+                KeyCode::Char('D') => {
+                    if matches!(self.current_state, AppState::ConvertBon)
+                        && let Some(i) = self.new_bon_list.state.selected()
+                        && let Some(entry) = self.new_bon_list.items.get(i).cloned()
+                    {
+                        self.bon_history
+                            .record(&mut self.new_bon_list, vec![Change::Insert(i + 1, entry)]);
+                        self.events.send(AppEvent::CalculateSummary);
+                    }
+                }
+                KeyCode::Char('J') => self.move_entry(true),
+                KeyCode::Char('K') => self.move_entry(false),
                 KeyCode::Enter => {
                     if matches!(self.current_state, AppState::Import) {
-                        let file_path = Path::new(&self.import_path);
                         if let Some(i) = self.import_list.state.selected() {
-                            let file_name = self.import_list.items[i].clone();
-                            self.ocr_file = file_path
-                                .join(file_name)
-                                .to_str()
-                                .expect("Couldn't convert path to string")
-                                .to_string();
+                            let path = self.import_list.items[i].path.clone();
+                            // warn once, pressing enter again imports the file anyway
+                            let warned = self
+                                .import_list
+                                .warning
+                                .as_ref()
+                                .is_some_and(|(file, _)| *file == path);
+                            if !warned && let Some(warning) = self.duplicate_warning(&path) {
+                                self.import_list.warning = Some((path, warning));
+                            } else {
+                                self.import_list.warning = None;
+                                self.ocr_file = path
+                                    .to_str()
+                                    .expect("Couldn't convert path to string")
+                                    .to_string();
+                                self.events.send(AppEvent::GoOcrState);
+                            }
+                        } else {
+                            self.events.send(AppEvent::GoOcrState);
+                        }
+                    } else if matches!(self.current_state, AppState::Batch) {
+                        // failed scans show their error in the list, there is nothing to review
+                        if let Some(i) = self.batch_list.state.selected()
+                            && let Some(entry) = self.batch_list.items.get(i)
+                            && entry.error.is_none()
+                        {
+                            self.ocr_file = entry.file.clone();
+                            self.ocr_history.clear();
+                            self.ocr_list.items = entry.lines.clone();
+                            self.ocr_list.state = ListState::default();
+                            self.ocr_list.status = None;
+                            if !self.ocr_list.items.is_empty() {
+                                self.ocr_list.state.select_first();
+                            }
+                            self.events.send(AppEvent::GoOcrState);
                         }
-                        self.events.send(AppEvent::GoOcrState);
                     } else if matches!(self.current_state, AppState::OCR) {
                         self.events.send(AppEvent::ConvertToBon);
                     } else if matches!(self.current_state, AppState::ConvertBon) {
-                        self.events.send(AppEvent::ImportBon);
-                    } else if matches!(self.current_state, AppState::Category) {
-                        if let Some(i) = self.category_list.state.selected() {
-                            if let Some(category) = self.category_list.items.get(i) {
-                                if let Some(j) = self.new_bon_list.state.selected() {
-                                    if let Some(item) = self.new_bon_list.items.get_mut(j) {
-                                        item.category = category.category.clone();
-                                    }
-                                }
+                        self.duplicate_bon = self
+                            .bon_draft()
+                            .find_duplicate(&self.database.get_bons())
+                            .cloned();
+                        if self.duplicate_bon.is_some() {
+                            self.events.send(AppEvent::GoDuplicateBonState);
+                        } else {
+                            self.events.send(AppEvent::ImportBon);
+                        }
+                    } else if matches!(self.current_state, AppState::Reconcile) {
+                        if let Some(i) = self.reconcile_list.bon_state.selected()
+                            && let Some(bon) = self.reconcile_list.bons.get(i)
+                            && let Some(j) = self.reconcile_list.transaction_state.selected()
+                            && let Some(transaction) = self.reconcile_list.transactions.get(j)
+                        {
+                            self.database
+                                .pair_transaction(transaction.transaction_id, Some(bon.bon_id));
+                        }
+                        self.events.send(AppEvent::UpdateFromDatabase);
+                    } else if matches!(self.current_state, AppState::Budgets) {
+                        if let Some(i) = self.budget_list.state.selected()
+                            && let Some(budget) = self.budget_list.items.get(i)
+                        {
+                            self.edit_field.move_cursor(CursorMove::End);
+                            self.edit_field.delete_line_by_head();
+                            if let Some(limit) = budget.limit {
+                                self.edit_field.insert_str(limit.to_string());
                             }
+                            self.events.send(AppEvent::GoEditBudgetState);
+                        }
+                    } else if matches!(self.current_state, AppState::Category) {
+                        if let Some(i) = self.category_list.state.selected()
+                            && let Some(category) = self.category_list.items.get(i)
+                        {
+                            let category_name = category.category.clone();
+                            let color = category.color.clone();
+                            self.edit_bon_entry(|entry| {
+                                entry.category = category_name;
+                                entry.color = color;
+                            });
                         }
                         self.events.send(AppEvent::GoConvertBonState);
+                        self.events.send(AppEvent::CalculateSummary);
+                    }
+                }
+                KeyCode::Tab => {
+                    if matches!(self.current_state, AppState::Reconcile) {
+                        self.reconcile_list.focus = match self.reconcile_list.focus {
+                            ReconcileFocus::Bons => ReconcileFocus::Transactions,
+                            ReconcileFocus::Transactions => ReconcileFocus::Pairs,
+                            ReconcileFocus::Pairs => ReconcileFocus::Bons,
+                        };
                     }
                 }
                 KeyCode::Esc => {
@@ -457,171 +1481,523 @@ impl App<'_> {
         Ok(())
     }
 
+    fn go_add_entry_state(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon) {
+            self.new_bon_list.completions.clear();
+            self.current_state = AppState::AddEntry;
+        }
+    }
+
+    fn go_batch_state(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon)
+            | matches!(self.current_state, AppState::DuplicateBon)
+            | matches!(self.current_state, AppState::Import)
+        {
+            self.current_state = AppState::Batch;
+        }
+    }
+
     fn go_blacklist_state(&mut self) {
         if matches!(self.current_state, AppState::OCR) {
             self.current_state = AppState::Blacklist;
         }
     }
 
+    fn go_bon_image_state(&mut self) {
+        if matches!(self.current_state, AppState::Home)
+            && let Some(i) = self.bon_list.state.selected()
+            && let Some(bon) = self.bon_list.items.get(i)
+        {
+            self.bon_image = BonImage::default();
+            match self.database.get_bon_image(bon.bon_id) {
+                Some(file) if Path::new(&file).exists() => self.load_bon_image(file),
+                Some(file) => {
+                    self.bon_image.status = Some(format!("{file} doesn't exist anymore"));
+                    self.bon_image.file = file;
+                }
+                None => {
+                    self.bon_image.status = Some(format!("No image stored for bon {}", bon.bon_id))
+                }
+            }
+            self.current_state = AppState::BonImage;
+        }
+    }
+
+    fn go_budgets_state(&mut self) {
+        if matches!(self.current_state, AppState::Home) {
+            self.current_state = AppState::Budgets;
+            self.events.send(AppEvent::UpdateFromDatabase);
+        } else if matches!(self.current_state, AppState::EditBudget) {
+            self.current_state = AppState::Budgets;
+        }
+    }
+
     fn go_category_state(&mut self) {
         if matches!(self.current_state, AppState::ConvertBon)
+            | matches!(self.current_state, AppState::DeleteCategory)
             | matches!(self.current_state, AppState::EditCategory)
+            | matches!(self.current_state, AppState::EditCategoryColor)
+            | matches!(self.current_state, AppState::MergeCategory)
+            | matches!(self.current_state, AppState::MoveCategory)
+            | matches!(self.current_state, AppState::RenameCategory)
         {
             if !self.category_list.items.is_empty() {
                 self.category_list.state.select_first();
             }
+            // a failed rename stays visible in the category list
+            if !matches!(self.current_state, AppState::RenameCategory) {
+                self.category_list.status = None;
+            }
             self.current_state = AppState::Category;
         }
     }
 
+    fn go_charts_state(&mut self) {
+        if matches!(self.current_state, AppState::Home) {
+            self.current_state = AppState::Charts;
+            self.events.send(AppEvent::UpdateFromDatabase);
+        }
+    }
+
     fn go_convert_bon_state(&mut self) {
         self.current_state = AppState::ConvertBon;
     }
 
+    fn go_delete_category_state(&mut self) {
+        if matches!(self.current_state, AppState::Category) {
+            self.current_state = AppState::DeleteCategory;
+        }
+    }
+
+    fn go_duplicate_bon_state(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon) {
+            self.current_state = AppState::DuplicateBon;
+        }
+    }
+
     fn go_edit_bon_price_state(&mut self) {
         if matches!(self.current_state, AppState::ConvertBon) {
             self.current_state = AppState::EditBonPrice;
         }
     }
 
+    fn go_edit_budget_state(&mut self) {
+        if matches!(self.current_state, AppState::Budgets) {
+            self.current_state = AppState::EditBudget;
+        }
+    }
+
     fn go_edit_category_state(&mut self) {
         if matches!(self.current_state, AppState::Category) {
             self.current_state = AppState::EditCategory;
         }
     }
 
+    fn go_edit_category_color_state(&mut self) {
+        if matches!(self.current_state, AppState::Category) {
+            self.current_state = AppState::EditCategoryColor;
+        }
+    }
+
+    fn go_edit_rule_state(&mut self) {
+        if matches!(self.current_state, AppState::Rules) {
+            self.rule_list.status = None;
+            self.current_state = AppState::EditRule;
+        }
+    }
+
+    fn go_edit_date_state(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon) {
+            self.current_state = AppState::EditDate;
+        }
+    }
+
     fn go_edit_name_state(&mut self) {
         if matches!(self.current_state, AppState::ConvertBon) {
             self.current_state = AppState::EditName;
         }
     }
 
+    fn go_edit_ocr_line_state(&mut self) {
+        if matches!(self.current_state, AppState::OCR) {
+            self.current_state = AppState::EditOcrLine;
+        }
+    }
+
     fn go_edit_price_state(&mut self) {
         if matches!(self.current_state, AppState::ConvertBon) {
             self.current_state = AppState::EditPrice;
         }
     }
 
+    fn go_edit_store_state(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon) {
+            self.current_state = AppState::EditStore;
+        }
+    }
+
+    fn go_export_csv_state(&mut self) {
+        if matches!(self.current_state, AppState::Home) {
+            self.bon_list.status = None;
+            self.current_state = AppState::ExportCsv;
+        }
+    }
+
     fn go_home_state(&mut self) {
+        self.ocr_history.clear();
         self.ocr_list.items.clear();
         self.ocr_list.state = ListState::default();
+        self.ocr_list.status = None;
         self.current_state = AppState::Home;
     }
 
     fn go_import_state(&mut self) {
         if matches!(self.current_state, AppState::Home) {
+            self.import_list.warning = None;
             self.current_state = AppState::Import;
         }
     }
 
+    fn go_import_statement_state(&mut self) {
+        if matches!(self.current_state, AppState::Reconcile) {
+            self.current_state = AppState::ImportStatement;
+        }
+    }
+
+    fn go_insert_ocr_line_state(&mut self) {
+        if matches!(self.current_state, AppState::OCR) {
+            self.current_state = AppState::InsertOcrLine;
+        }
+    }
+
+    fn go_merge_category_state(&mut self) {
+        if matches!(self.current_state, AppState::Category)
+            | matches!(self.current_state, AppState::RenameCategory)
+        {
+            self.current_state = AppState::MergeCategory;
+        }
+    }
+
+    fn go_move_category_state(&mut self) {
+        if matches!(self.current_state, AppState::Category) {
+            self.current_state = AppState::MoveCategory;
+        }
+    }
+
     fn go_ocr_state(&mut self) {
         self.current_state = AppState::OCR;
-        if self.ocr_list.items.is_empty() {
+        if self.bon_image.file != self.ocr_file && !self.ocr_file.is_empty() {
+            self.load_bon_image(self.ocr_file.clone());
+        }
+        if self.ocr_list.items.is_empty()
+            && let Some(lines) = self.ocr_cache.get(&self.ocr_file)
+        {
+            self.ocr_list.items = lines.clone();
+            if !self.ocr_list.items.is_empty() {
+                self.ocr_list.state.select_first();
+            }
+        } else if self.ocr_list.items.is_empty() {
             self.ocr_list.items = vec![OcrEntry {
                 name: "Processing..".to_string(),
                 ocr_type: OcrType::Entry,
+                region: None,
             }];
             self.events.send(AppEvent::PerformOCR);
         }
     }
 
-    fn hide_item(&mut self) {
+    fn go_price_history_state(&mut self) {
         if matches!(self.current_state, AppState::Home) {
-            if let Some(i) = self.bon_list.state.selected() {
-                if let Some(entry) = self.bon_list.items.get(i) {
-                    self.database.hide_bon(entry.bon_id);
-                    self.events.send(AppEvent::UpdateFromDatabase);
-                }
+            self.current_state = AppState::PriceHistory;
+            self.events.send(AppEvent::UpdateFromDatabase);
+        }
+    }
+
+    fn go_reconcile_state(&mut self) {
+        if matches!(self.current_state, AppState::Home) {
+            self.reconcile_list.status = None;
+            self.current_state = AppState::Reconcile;
+            self.events.send(AppEvent::UpdateFromDatabase);
+        } else if matches!(self.current_state, AppState::ImportStatement) {
+            self.current_state = AppState::Reconcile;
+        }
+    }
+
+    fn go_rename_category_state(&mut self) {
+        if matches!(self.current_state, AppState::Category) {
+            self.current_state = AppState::RenameCategory;
+        }
+    }
+
+    fn go_reports_state(&mut self) {
+        if matches!(self.current_state, AppState::Home) {
+            self.current_state = AppState::Reports;
+            self.events.send(AppEvent::UpdateFromDatabase);
+        }
+    }
+
+    fn go_rule_category_state(&mut self) {
+        if matches!(self.current_state, AppState::EditRule) {
+            if !self.category_list.items.is_empty() {
+                self.category_list.state.select_first();
             }
+            self.current_state = AppState::RuleCategory;
+        }
+    }
+
+    fn go_rules_state(&mut self) {
+        if matches!(self.current_state, AppState::Home)
+            | matches!(self.current_state, AppState::EditRule)
+            | matches!(self.current_state, AppState::RuleCategory)
+        {
+            if matches!(self.current_state, AppState::Home) {
+                self.rule_list.status = None;
+                self.rule_list.updated = None;
+            }
+            if !self.rule_list.items.is_empty() && self.rule_list.state.selected().is_none() {
+                self.rule_list.state.select_first();
+            }
+            self.current_state = AppState::Rules;
+        }
+    }
+
+    fn go_split_ocr_line_state(&mut self) {
+        if matches!(self.current_state, AppState::OCR) {
+            self.current_state = AppState::SplitOcrLine;
+        }
+    }
+
+    fn hide_item(&mut self) {
+        if matches!(self.current_state, AppState::Home)
+            && let Some(i) = self.bon_list.state.selected()
+            && let Some(entry) = self.bon_list.items.get(i)
+        {
+            self.database.hide_bon(entry.bon_id);
+            self.events.send(AppEvent::UpdateFromDatabase);
         }
     }
 
     fn import_bon(&mut self) {
-        let mut split = self.new_bon_list.date.split(".").collect::<Vec<&str>>();
-        split.reverse();
-        let date = split.join("-");
-        self.database
-            .create_bon(date.as_str(), self.new_bon_list.price_ocr);
-        let bon_id = self.database.get_last_bon_id();
-        self.new_bon_list.items.iter().for_each(|entry| {
-            let categories = self.database.get_categories();
-            let category_id = categories
-                .iter()
-                .find(|cat| cat.category == entry.category)
-                .map_or_else(
-                    || {
-                        self.database.create_category(entry.category.as_str());
-                        self.database.get_last_category_id()
-                    },
-                    |cat| cat.category_id,
-                );
-            let products = self.database.get_products();
-            let product_id = products
+        let draft = self.bon_draft();
+        self.duplicate_bon = None;
+        self.bon_list.status = None;
+        let file = self.ocr_file.clone();
+        let bon_id = scan::import(&self.database, &draft, &file);
+        self.archive(bon_id, &draft, &file);
+        draft
+            .items
+            .iter()
+            .for_each(|entry| self.classifier.train(&entry.product, &entry.category));
+        self.ocr_cache.remove(&self.ocr_file);
+        let batch_len = self.batch_list.items.len();
+        self.batch_list
+            .items
+            .retain(|entry| entry.file != self.ocr_file);
+        self.ocr_file = String::new();
+        if self.batch_list.items.len() < batch_len {
+            if self
+                .batch_list
+                .state
+                .selected()
+                .is_some_and(|i| i >= self.batch_list.items.len())
+            {
+                self.batch_list.state.select_last();
+            }
+            self.events.send(AppEvent::GoBatchState);
+        } else {
+            self.events.send(AppEvent::GoHomeState);
+        }
+        self.events.send(AppEvent::UpdateFromDatabase);
+        self.events.send(AppEvent::CalculateSummary);
+    }
+
+    fn import_file_ready(&mut self, file: String) {
+        if self.background_ocr
+            && self
+                .import_list
+                .items
                 .iter()
-                .find(|prod| prod.product == entry.product)
-                .map_or_else(
-                    || {
-                        self.database
-                            .create_product(category_id, entry.product.as_str());
-                        self.database.get_last_product_id()
-                    },
-                    |cat| cat.category_id,
-                );
-            self.database.create_entry(bon_id, product_id, entry.price);
+                .any(|entry| entry.path == Path::new(&file))
+            && !self.ocr_cache.contains_key(&file)
+            && !self.ocr_scanning.contains(&file)
+            && !self.batch_list.pending.contains(&file)
+        {
+            self.ocr_scanning.push(file.clone());
+            let blacklist = self.ocr_blacklist.clone();
+            self.events.spawn_blocking(move |send| {
+                let result = scan::ocr(&file, &blacklist).map_err(|error| error.to_string());
+                send(AppEvent::BackgroundScanned(file, result));
+            });
+        }
+    }
+
+    fn load_bon_image(&mut self, file: String) {
+        self.bon_image = BonImage {
+            file: file.clone(),
+            status: Some("Loading..".to_string()),
+            ..Default::default()
+        };
+        self.events.spawn_blocking(move |send| {
+            // a thumbnail is enough for the terminal and keeps redrawing cheap
+            let result = discovery::with_decodable(Path::new(&file), |path| image::open(path))
+                .map_err(|error| error.to_string())
+                .and_then(|image| image.map_err(|error| error.to_string()))
+                .map(|image| {
+                    let original_size = (image.width(), image.height());
+                    (image.thumbnail(480, 480).to_rgb8(), original_size)
+                });
+            send(AppEvent::BonImageLoaded(file, result));
         });
-        let ocr_file = self.ocr_file.clone();
+    }
+
+    fn manual_bon(&mut self) {
+        self.bon_history.clear();
         self.ocr_file = String::new();
-        let file_name = Path::new(&ocr_file)
-            .file_name()
-            .expect("Could not get file name")
-            .to_str()
-            .expect("Could not convert file name to string");
-        self.database.add_processed_entry(file_name);
-        self.events.send(AppEvent::GoHomeState);
-        self.events.send(AppEvent::UpdateFromDatabase);
+        self.new_bon_list.date = self.database.get_current_date();
+        self.new_bon_list.items.clear();
+        self.new_bon_list.price_calc = 0.0;
+        self.new_bon_list.price_ocr = 0.0;
+        self.new_bon_list.state = ListState::default();
+        self.new_bon_list.store = String::new();
+        self.events.send(AppEvent::GoConvertBonState);
         self.events.send(AppEvent::CalculateSummary);
     }
 
+    fn move_category(&mut self, target_id: i64) {
+        if target_id == self.category_source {
+            self.database
+                .set_category_parent(self.category_source, None);
+        } else if !database::Category::is_descendant_of(
+            &self.category_list.items,
+            target_id,
+            self.category_source,
+        ) {
+            self.database
+                .set_category_parent(self.category_source, Some(target_id));
+        }
+    }
+
+    fn move_entry(&mut self, down: bool) {
+        if matches!(self.current_state, AppState::ConvertBon)
+            && let Some(i) = self.new_bon_list.state.selected()
+        {
+            let j = if down { i + 1 } else { i.wrapping_sub(1) };
+            if j < self.new_bon_list.items.len() {
+                self.bon_history
+                    .record(&mut self.new_bon_list, vec![Change::Swap(i, j)]);
+            }
+        }
+    }
+
     pub fn new() -> Self {
         Self::default()
     }
 
     fn next_item(&mut self) {
         match self.current_state {
-            AppState::Category => {
-                if let Some(i) = self.category_list.state.selected() {
-                    if i < self.category_list.items.len() - 1 {
-                        self.category_list.state.select_next();
-                    }
+            AppState::Batch => {
+                if let Some(i) = self.batch_list.state.selected()
+                    && i < self.batch_list.items.len() - 1
+                {
+                    self.batch_list.state.select_next();
+                }
+            }
+            AppState::Budgets => {
+                if let Some(i) = self.budget_list.state.selected()
+                    && i < self.budget_list.items.len() - 1
+                {
+                    self.budget_list.state.select_next();
+                }
+            }
+            AppState::Category
+            | AppState::DeleteCategory
+            | AppState::MergeCategory
+            | AppState::MoveCategory
+            | AppState::RuleCategory => {
+                if let Some(i) = self.category_list.state.selected()
+                    && i < self.category_list.items.len() - 1
+                {
+                    self.category_list.state.select_next();
+                }
+            }
+            AppState::Charts => {
+                if let Some(i) = self.chart_list.products.state.selected()
+                    && i < self.chart_list.products.items.len() - 1
+                {
+                    self.chart_list.products.state.select_next();
+                    self.update_price_history();
                 }
             }
             AppState::ConvertBon => {
-                if let Some(i) = self.new_bon_list.state.selected() {
-                    if i < self.new_bon_list.items.len() - 1 {
-                        self.new_bon_list.state.select_next();
-                    }
+                if let Some(i) = self.new_bon_list.state.selected()
+                    && i < self.new_bon_list.items.len() - 1
+                {
+                    self.new_bon_list.state.select_next();
                 }
             }
             AppState::Home => {
-                if let Some(i) = self.bon_list.state.selected() {
-                    if i < self.bon_list.items.len() - 1 {
-                        self.bon_list.state.select_next();
-                        self.events.send(AppEvent::CalculateSummary);
-                    }
+                if let Some(i) = self.bon_list.state.selected()
+                    && i < self.bon_list.items.len() - 1
+                {
+                    self.bon_list.state.select_next();
+                    self.events.send(AppEvent::CalculateSummary);
                 }
             }
             AppState::Import => {
-                if let Some(i) = self.import_list.state.selected() {
-                    if i < self.import_list.items.len() - 1 {
-                        self.import_list.state.select_next();
-                    }
+                if let Some(i) = self.import_list.state.selected()
+                    && i < self.import_list.items.len() - 1
+                {
+                    self.import_list.state.select_next();
                 }
             }
             AppState::OCR => {
-                if let Some(i) = self.ocr_list.state.selected() {
-                    if i < self.ocr_list.items.len() - 1 {
-                        self.ocr_list.state.select_next();
-                    }
+                if let Some(i) = self.ocr_list.state.selected()
+                    && i < self.ocr_list.items.len() - 1
+                {
+                    self.ocr_list.state.select_next();
+                }
+            }
+            AppState::PriceHistory => {
+                if let Some(i) = self.price_list.products.state.selected()
+                    && i < self.price_list.products.items.len() - 1
+                {
+                    self.price_list.products.state.select_next();
+                    self.update_price_history();
+                }
+            }
+            AppState::Reconcile => {
+                let (state, len) = match self.reconcile_list.focus {
+                    ReconcileFocus::Bons => (
+                        &mut self.reconcile_list.bon_state,
+                        self.reconcile_list.bons.len(),
+                    ),
+                    ReconcileFocus::Pairs => (
+                        &mut self.reconcile_list.pair_state,
+                        self.reconcile_list.pairs.len(),
+                    ),
+                    ReconcileFocus::Transactions => (
+                        &mut self.reconcile_list.transaction_state,
+                        self.reconcile_list.transactions.len(),
+                    ),
+                };
+                if let Some(i) = state.selected()
+                    && i < len - 1
+                {
+                    state.select_next();
+                }
+            }
+            AppState::Reports => {
+                if let Some(i) = self.report_list.state.selected()
+                    && i < self.report_list.items.len() - 1
+                {
+                    self.report_list.state.select_next();
+                }
+            }
+            AppState::Rules => {
+                if let Some(i) = self.rule_list.state.selected()
+                    && i < self.rule_list.items.len() - 1
+                {
+                    self.rule_list.state.select_next();
                 }
             }
             _ => {}
@@ -635,13 +2011,13 @@ impl App<'_> {
             .iter()
             .filter(|elem| matches!(elem.ocr_type, OcrType::Date))
             .count();
-        if let Some(i) = self.ocr_list.state.selected() {
-            if let Some(entry) = self.ocr_list.items.get_mut(i) {
-                if dates == 0 && matches!(entry.ocr_type, OcrType::Entry) {
-                    entry.ocr_type = OcrType::Date;
-                } else if matches!(entry.ocr_type, OcrType::Date) {
-                    entry.ocr_type = OcrType::Entry;
-                }
+        if let Some(i) = self.ocr_list.state.selected()
+            && let Some(entry) = self.ocr_list.items.get(i)
+        {
+            if dates == 0 && matches!(entry.ocr_type, OcrType::Entry) {
+                self.edit_ocr_line(|entry| entry.ocr_type = OcrType::Date);
+            } else if matches!(entry.ocr_type, OcrType::Date) {
+                self.edit_ocr_line(|entry| entry.ocr_type = OcrType::Entry);
             }
         }
     }
@@ -653,113 +2029,197 @@ impl App<'_> {
             .iter()
             .filter(|elem| matches!(elem.ocr_type, OcrType::Sum))
             .count();
-        if let Some(i) = self.ocr_list.state.selected() {
-            if let Some(entry) = self.ocr_list.items.get_mut(i) {
-                if sums == 0 && matches!(entry.ocr_type, OcrType::Entry) {
-                    entry.ocr_type = OcrType::Sum;
-                } else if matches!(entry.ocr_type, OcrType::Sum) {
-                    entry.ocr_type = OcrType::Entry;
-                }
+        if let Some(i) = self.ocr_list.state.selected()
+            && let Some(entry) = self.ocr_list.items.get(i)
+        {
+            if sums == 0 && matches!(entry.ocr_type, OcrType::Entry) {
+                self.edit_ocr_line(|entry| entry.ocr_type = OcrType::Sum);
+            } else if matches!(entry.ocr_type, OcrType::Sum) {
+                self.edit_ocr_line(|entry| entry.ocr_type = OcrType::Entry);
             }
         }
     }
 
-    pub fn perform_ocr(&mut self) {
-        let img = Image::from_path(&self.ocr_file).expect("Failed to load image for OCR");
-
-        let args = Args {
-            lang: "deu".to_string(),
-            config_variables: HashMap::from([(
-                "tessedit_char_whitelist".into(),
-                "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZöäüÖÄÜß1234567890., &-%$@€:"
-                    .into(),
-            )]),
-            dpi: Some(150),
-            psm: Some(6),
-            oem: Some(3),
-        };
+    fn open_bon_image(&mut self) {
+        if self.bon_image.file.is_empty() {
+            return;
+        }
+        self.bon_image.status = Some(match open::that_detached(&self.bon_image.file) {
+            Ok(()) => format!("Opened {} in the image viewer", self.bon_image.file),
+            Err(error) => format!("Couldn't open {}: {}", self.bon_image.file, error),
+        });
+    }
 
-        let ocr_text =
-            rusty_tesseract::image_to_string(&img, &args).expect("Could not perform OCR");
-
-        self.ocr_list.items = ocr_text
-            .split('\n')
-            .map(|line| line.trim().to_string())
-            .filter(|line| line.len() > 1)
-            .map(|line| {
-                // delete the last element, when it's a single character
-                let re = Regex::new(r" \w$").expect("Could not compile regex");
-                if let Some(found) = re.find(&line) {
-                    line[..found.start()].to_string()
-                } else {
-                    line.to_string()
-                }
-            })
-            .filter(|line| {
-                // the last element of the line must contain a digit
-                let elems = line.split(" ").collect::<Vec<&str>>();
-                let re = Regex::new(r"\d").expect("Could not compile regex");
-                re.is_match(elems[elems.len() - 1])
-            })
-            .filter(|line| {
-                // the line must contain some sort of delimiter
-                let re = Regex::new(r"[,.:-]").expect("Could not compile regex");
-                re.is_match(line)
-            })
-            .filter(|line| !self.ocr_blacklist.iter().any(|elem| line.contains(elem)))
-            .map(|line| OcrEntry {
-                name: line,
-                ocr_type: OcrType::Entry,
-            })
-            .collect::<Vec<OcrEntry>>();
+    pub fn perform_ocr(&mut self) {
+        self.ocr_history.clear();
+        match scan::ocr(&self.ocr_file, &self.ocr_blacklist) {
+            Ok(lines) => {
+                self.ocr_list.items = lines;
+                self.ocr_list.status = None;
+            }
+            Err(error) => {
+                self.ocr_list.items.clear();
+                self.ocr_list.status = Some(format!("OCR failed: {}", error));
+            }
+        }
 
-        if !self.ocr_list.items.is_empty() {
+        if self.ocr_list.items.is_empty() {
+            self.ocr_list.state.select(None);
+        } else {
             self.ocr_list.state.select_first();
         }
     }
 
     fn previous_item(&mut self) {
         match self.current_state {
-            AppState::Category => {
-                if let Some(i) = self.category_list.state.selected() {
-                    if i > 0 {
-                        self.category_list.state.select_previous();
-                    }
+            AppState::Batch => {
+                if let Some(i) = self.batch_list.state.selected()
+                    && i > 0
+                {
+                    self.batch_list.state.select_previous();
+                }
+            }
+            AppState::Budgets => {
+                if let Some(i) = self.budget_list.state.selected()
+                    && i > 0
+                {
+                    self.budget_list.state.select_previous();
+                }
+            }
+            AppState::Category
+            | AppState::DeleteCategory
+            | AppState::MergeCategory
+            | AppState::MoveCategory
+            | AppState::RuleCategory => {
+                if let Some(i) = self.category_list.state.selected()
+                    && i > 0
+                {
+                    self.category_list.state.select_previous();
+                }
+            }
+            AppState::Charts => {
+                if let Some(i) = self.chart_list.products.state.selected()
+                    && i > 0
+                {
+                    self.chart_list.products.state.select_previous();
+                    self.update_price_history();
                 }
             }
             AppState::ConvertBon => {
-                if let Some(i) = self.new_bon_list.state.selected() {
-                    if i > 0 {
-                        self.new_bon_list.state.select_previous();
-                    }
+                if let Some(i) = self.new_bon_list.state.selected()
+                    && i > 0
+                {
+                    self.new_bon_list.state.select_previous();
                 }
             }
             AppState::Home => {
-                if let Some(i) = self.bon_list.state.selected() {
-                    if i > 0 {
-                        self.bon_list.state.select_previous();
-                        self.events.send(AppEvent::CalculateSummary);
-                    }
+                if let Some(i) = self.bon_list.state.selected()
+                    && i > 0
+                {
+                    self.bon_list.state.select_previous();
+                    self.events.send(AppEvent::CalculateSummary);
                 }
             }
             AppState::Import => {
-                if let Some(i) = self.import_list.state.selected() {
-                    if i > 0 {
-                        self.import_list.state.select_previous();
-                    }
+                if let Some(i) = self.import_list.state.selected()
+                    && i > 0
+                {
+                    self.import_list.state.select_previous();
                 }
             }
             AppState::OCR => {
-                if let Some(i) = self.ocr_list.state.selected() {
-                    if i > 0 {
-                        self.ocr_list.state.select_previous();
-                    }
+                if let Some(i) = self.ocr_list.state.selected()
+                    && i > 0
+                {
+                    self.ocr_list.state.select_previous();
+                }
+            }
+            AppState::PriceHistory => {
+                if let Some(i) = self.price_list.products.state.selected()
+                    && i > 0
+                {
+                    self.price_list.products.state.select_previous();
+                    self.update_price_history();
+                }
+            }
+            AppState::Reconcile => {
+                let state = match self.reconcile_list.focus {
+                    ReconcileFocus::Bons => &mut self.reconcile_list.bon_state,
+                    ReconcileFocus::Pairs => &mut self.reconcile_list.pair_state,
+                    ReconcileFocus::Transactions => &mut self.reconcile_list.transaction_state,
+                };
+                if let Some(i) = state.selected()
+                    && i > 0
+                {
+                    state.select_previous();
+                }
+            }
+            AppState::Reports => {
+                if let Some(i) = self.report_list.state.selected()
+                    && i > 0
+                {
+                    self.report_list.state.select_previous();
+                }
+            }
+            AppState::Rules => {
+                if let Some(i) = self.rule_list.state.selected()
+                    && i > 0
+                {
+                    self.rule_list.state.select_previous();
                 }
             }
             _ => {}
         }
     }
 
+    fn reassign_category(&mut self, target_id: i64) {
+        let source = self
+            .category_list
+            .items
+            .iter()
+            .find(|category| category.category_id == self.category_source);
+        let target = self
+            .category_list
+            .items
+            .iter()
+            .find(|category| category.category_id == target_id);
+        if let Some(source) = source
+            && let Some(target) = target
+        {
+            if matches!(self.current_state, AppState::MergeCategory) {
+                self.database
+                    .merge_categories(source.category_id, target.category_id);
+            } else {
+                self.database
+                    .delete_category(source.category_id, target.category_id);
+            }
+            self.new_bon_list
+                .items
+                .iter_mut()
+                .filter(|item| item.category == source.category)
+                .for_each(|item| {
+                    item.category = target.category.clone();
+                    item.color = target.color.clone();
+                });
+            self.retrain_classifier();
+        }
+    }
+
+    fn redo(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon) {
+            if self.bon_history.redo(&mut self.new_bon_list) {
+                self.events.send(AppEvent::CalculateSummary);
+            }
+        } else if matches!(self.current_state, AppState::OCR) {
+            self.ocr_history.redo(&mut self.ocr_list);
+        }
+    }
+
+    fn retrain_classifier(&mut self) {
+        self.classifier =
+            classifier::Classifier::from_training_data(&self.database.get_training_data());
+    }
+
     pub async fn run(mut self, mut terminal: DefaultTerminal) -> color_eyre::Result<()> {
         if !self.bon_list.items.is_empty() {
             self.bon_list.state.select_first();
@@ -784,26 +2244,60 @@ impl App<'_> {
                     }
                 }
                 Event::App(app_event) => match app_event {
+                    AppEvent::ApplyRules => self.apply_rules(),
+                    AppEvent::BackgroundScanned(file, result) => {
+                        self.background_scanned(file, result)
+                    }
+                    AppEvent::BatchScanned(file, result) => self.batch_scanned(file, result),
+                    AppEvent::BonImageLoaded(file, result) => self.bon_image_loaded(file, result),
                     AppEvent::CalculateSummary => self.calculate_summary(),
                     AppEvent::ConvertToBon => self.convert_to_bon(),
+                    AppEvent::GoAddEntryState => self.go_add_entry_state(),
+                    AppEvent::GoBatchState => self.go_batch_state(),
                     AppEvent::GoBlacklistState => self.go_blacklist_state(),
+                    AppEvent::GoBonImageState => self.go_bon_image_state(),
+                    AppEvent::GoBudgetsState => self.go_budgets_state(),
                     AppEvent::GoCategoryState => self.go_category_state(),
+                    AppEvent::GoChartsState => self.go_charts_state(),
                     AppEvent::GoConvertBonState => self.go_convert_bon_state(),
+                    AppEvent::GoDeleteCategoryState => self.go_delete_category_state(),
+                    AppEvent::GoDuplicateBonState => self.go_duplicate_bon_state(),
                     AppEvent::GoEditBonPriceState => self.go_edit_bon_price_state(),
+                    AppEvent::GoEditBudgetState => self.go_edit_budget_state(),
+                    AppEvent::GoEditCategoryColorState => self.go_edit_category_color_state(),
                     AppEvent::GoEditCategoryState => self.go_edit_category_state(),
+                    AppEvent::GoEditDateState => self.go_edit_date_state(),
                     AppEvent::GoEditNameState => self.go_edit_name_state(),
+                    AppEvent::GoEditOcrLineState => self.go_edit_ocr_line_state(),
                     AppEvent::GoEditPriceState => self.go_edit_price_state(),
+                    AppEvent::GoEditRuleState => self.go_edit_rule_state(),
+                    AppEvent::GoEditStoreState => self.go_edit_store_state(),
+                    AppEvent::GoExportCsvState => self.go_export_csv_state(),
                     AppEvent::GoHomeState => self.go_home_state(),
                     AppEvent::GoImportState => self.go_import_state(),
+                    AppEvent::GoInsertOcrLineState => self.go_insert_ocr_line_state(),
+                    AppEvent::GoImportStatementState => self.go_import_statement_state(),
+                    AppEvent::GoMergeCategoryState => self.go_merge_category_state(),
+                    AppEvent::GoMoveCategoryState => self.go_move_category_state(),
                     AppEvent::GoOcrState => self.go_ocr_state(),
+                    AppEvent::GoPriceHistoryState => self.go_price_history_state(),
+                    AppEvent::GoReconcileState => self.go_reconcile_state(),
+                    AppEvent::GoRenameCategoryState => self.go_rename_category_state(),
+                    AppEvent::GoReportsState => self.go_reports_state(),
+                    AppEvent::GoRuleCategoryState => self.go_rule_category_state(),
+                    AppEvent::GoRulesState => self.go_rules_state(),
+                    AppEvent::GoSplitOcrLineState => self.go_split_ocr_line_state(),
                     AppEvent::HideItem => self.hide_item(),
                     AppEvent::ImportBon => self.import_bon(),
+                    AppEvent::ImportFileReady(file) => self.import_file_ready(file),
                     AppEvent::NextItem => self.next_item(),
                     AppEvent::PerformOCR => self.perform_ocr(),
                     AppEvent::PreviousItem => self.previous_item(),
+                    AppEvent::StartBatch => self.start_batch(),
                     AppEvent::OcrMarkDate => self.ocr_mark_date(),
                     AppEvent::OcrMarkSum => self.ocr_mark_sum(),
                     AppEvent::UpdateFromDatabase => self.update_from_database(),
+                    AppEvent::UpdateImportList => self.update_import_list(),
                     AppEvent::Quit => self.quit(),
                 },
             }
@@ -811,33 +2305,396 @@ impl App<'_> {
         Ok(())
     }
 
+    fn start_batch(&mut self) {
+        if matches!(self.current_state, AppState::Import) {
+            let files: Vec<String> = self
+                .import_list
+                .items
+                .iter()
+                .map(|file| {
+                    file.path
+                        .to_str()
+                        .expect("Couldn't convert path to string")
+                        .to_string()
+                })
+                .filter(|file| {
+                    !self.batch_list.pending.contains(file)
+                        && !self
+                            .batch_list
+                            .items
+                            .iter()
+                            .any(|entry| &entry.file == file)
+                })
+                .collect();
+            let (cached, files): (Vec<String>, Vec<String>) = files
+                .into_iter()
+                .partition(|file| self.ocr_cache.contains_key(file));
+            cached.into_iter().for_each(|file| {
+                let lines = self.ocr_cache.remove(&file).unwrap_or_default();
+                self.batch_scanned(file, Ok(lines));
+            });
+            // every worker scans its share of the files one after another
+            let workers = std::thread::available_parallelism().map_or(1, |workers| workers.get());
+            for chunk in files.chunks(files.len().div_ceil(workers).max(1)) {
+                let chunk = chunk.to_vec();
+                let blacklist = self.ocr_blacklist.clone();
+                self.events.spawn_blocking(move |send| {
+                    chunk.into_iter().for_each(|file| {
+                        let result =
+                            scan::ocr(&file, &blacklist).map_err(|error| error.to_string());
+                        send(AppEvent::BatchScanned(file, result));
+                    })
+                });
+            }
+            self.batch_list.pending.extend(files);
+            self.events.send(AppEvent::GoBatchState);
+        }
+    }
+
     pub fn update_from_database(&mut self) {
         if matches!(self.current_state, AppState::OCR) {
             self.ocr_blacklist = self.database.get_blacklist();
-            let ocr_list = self.ocr_list.items.clone();
-            self.ocr_list.items = ocr_list
-                .into_iter()
-                .filter(|line| {
-                    !self
-                        .ocr_blacklist
+            // removed back to front, so the indices of the remaining lines stay valid
+            let blacklisted = self
+                .ocr_list
+                .items
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, line)| {
+                    self.ocr_blacklist
                         .iter()
                         .any(|elem| line.name.contains(elem))
                 })
-                .collect::<Vec<OcrEntry>>();
+                .map(|(i, line)| Change::Remove(i, line.clone()))
+                .collect::<Vec<Change<OcrList>>>();
+            self.ocr_history.record(&mut self.ocr_list, blacklisted);
         } else if matches!(self.current_state, AppState::Home) {
             self.bon_list.items = self.database.get_bons();
             if !self.bon_list.items.is_empty() {
                 self.bon_list.state.select_first();
             }
-            self.import_list.items = read_ocr_files(&self.database.get_processed());
+            self.import_list.items =
+                read_ocr_files(&self.discovery, &self.database.get_processed_files());
             if !self.import_list.items.is_empty() {
                 self.import_list.state.select_first();
             }
+        } else if matches!(self.current_state, AppState::Batch) {
+            self.bon_list.items = self.database.get_bons();
+            self.import_list.items =
+                read_ocr_files(&self.discovery, &self.database.get_processed_files());
+            if self.import_list.items.is_empty() {
+                self.import_list.state.select(None);
+            } else {
+                self.import_list.state.select_first();
+            }
+        } else if matches!(self.current_state, AppState::Budgets) {
+            self.budget_list.month = self.database.get_current_month();
+            self.budget_list.items = self.database.get_budgets(&self.budget_list.month);
+            if self.budget_list.items.is_empty() {
+                self.budget_list.state.select(None);
+            } else if self
+                .budget_list
+                .state
+                .selected()
+                .is_none_or(|i| i >= self.budget_list.items.len())
+            {
+                self.budget_list.state.select_first();
+            }
         } else if matches!(self.current_state, AppState::Category) {
             self.category_list.items = self.database.get_categories();
             if !self.category_list.items.is_empty() {
                 self.category_list.state.select_first();
             }
+            self.new_bon_list.items.iter_mut().for_each(|item| {
+                item.color = self
+                    .category_list
+                    .items
+                    .iter()
+                    .find(|category| category.category == item.category)
+                    .and_then(|category| category.color.clone());
+            });
+            self.bon_list.items = self.database.get_bons();
+        } else if matches!(self.current_state, AppState::Charts) {
+            self.chart_list.reports = self.database.get_report(database::ReportPeriod::Month);
+            self.chart_list.products.load(self.database.get_products());
+            self.update_price_history();
+        } else if matches!(self.current_state, AppState::PriceHistory) {
+            self.price_list.alerts = self.database.get_price_alerts(self.price_threshold);
+            self.price_list.products.load(self.database.get_products());
+            self.update_price_history();
+        } else if matches!(self.current_state, AppState::Reconcile) {
+            let mut transactions = self.database.get_transactions();
+            let mut bons = self.database.get_bons();
+            let mut pairs: Vec<(database::Bon, database::Transaction)> = Vec::new();
+            transactions.retain(|transaction| transaction.bon_id.is_none());
+            for transaction in self
+                .database
+                .get_transactions()
+                .into_iter()
+                .filter(|transaction| transaction.bon_id.is_some())
+            {
+                if let Some(i) = bons
+                    .iter()
+                    .position(|bon| Some(bon.bon_id) == transaction.bon_id)
+                {
+                    pairs.push((bons.remove(i), transaction));
+                }
+            }
+            self.reconcile_list.bons = bons;
+            self.reconcile_list.pairs = pairs;
+            self.reconcile_list.transactions = transactions;
+            for (state, len) in [
+                (
+                    &mut self.reconcile_list.bon_state,
+                    self.reconcile_list.bons.len(),
+                ),
+                (
+                    &mut self.reconcile_list.pair_state,
+                    self.reconcile_list.pairs.len(),
+                ),
+                (
+                    &mut self.reconcile_list.transaction_state,
+                    self.reconcile_list.transactions.len(),
+                ),
+            ] {
+                if len == 0 {
+                    state.select(None);
+                } else if state.selected().is_none_or(|i| i >= len) {
+                    state.select_first();
+                }
+            }
+        } else if matches!(self.current_state, AppState::Reports) {
+            self.report_list.items = self.database.get_report(self.report_list.period);
+            if self.report_list.items.is_empty() {
+                self.report_list.state.select(None);
+            } else {
+                self.report_list.state.select_last();
+            }
+        } else if matches!(self.current_state, AppState::Rules) {
+            self.rule_list.items = self.database.get_rules();
+            if self.rule_list.items.is_empty() {
+                self.rule_list.state.select(None);
+            } else if self
+                .rule_list
+                .state
+                .selected()
+                .is_none_or(|i| i >= self.rule_list.items.len())
+            {
+                self.rule_list.state.select_first();
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if matches!(self.current_state, AppState::ConvertBon) {
+            if self.bon_history.undo(&mut self.new_bon_list) {
+                self.events.send(AppEvent::CalculateSummary);
+            }
+        } else if matches!(self.current_state, AppState::OCR) {
+            self.ocr_history.undo(&mut self.ocr_list);
+        }
+    }
+
+    fn update_budget_warnings(&mut self) {
+        let mut split = self.new_bon_list.date.split(".").collect::<Vec<&str>>();
+        split.reverse();
+        let month = if split.len() == 3 {
+            format!("{}-{}", split[0], split[1])
+        } else {
+            self.database.get_current_month()
+        };
+        let categories = &self.category_list.items;
+        self.new_bon_list.warnings = self
+            .database
+            .get_budgets(&month)
+            .iter()
+            .filter_map(|budget| {
+                let limit = budget.limit?;
+                let added: f64 = self
+                    .new_bon_list
+                    .items
+                    .iter()
+                    .filter(|item| {
+                        categories.iter().any(|category| {
+                            category.category == item.category
+                                && (category.category_id == budget.category_id
+                                    || database::Category::is_descendant_of(
+                                        categories,
+                                        category.category_id,
+                                        budget.category_id,
+                                    ))
+                        })
+                    })
+                    .map(|item| item.price)
+                    .sum();
+                if added > 0.0 && budget.spent + added > limit {
+                    Some(format!(
+                        "{}: {:.2} of {:.2} €",
+                        budget.category,
+                        budget.spent + added,
+                        limit
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+    }
+
+    fn update_import_list(&mut self) {
+        let selected = self
+            .import_list
+            .state
+            .selected()
+            .and_then(|i| self.import_list.items.get(i).cloned());
+        self.import_list.items =
+            read_ocr_files(&self.discovery, &self.database.get_processed_files());
+        if self.import_list.items.is_empty() {
+            self.import_list.state.select(None);
+        } else {
+            self.import_list.state.select(Some(
+                selected
+                    .and_then(|file| self.import_list.items.iter().position(|elem| *elem == file))
+                    .unwrap_or(0),
+            ));
+        }
+    }
+
+    fn update_price_history(&mut self) {
+        if matches!(self.current_state, AppState::Charts) {
+            self.chart_list.products.update_history(&self.database);
+        } else if matches!(self.current_state, AppState::PriceHistory) {
+            self.price_list.products.update_history(&self.database);
+        }
+    }
+
+    pub fn with_settings(settings: settings::Settings) -> Self {
+        let database_exists = settings.database_exists();
+        let database = database::Database::new(&settings.database_file);
+        if !database_exists {
+            database.create_database();
+        } else {
+            database.update_database();
+        }
+        let bons = database.get_bons();
+        let blacklist = database.get_blacklist();
+        let processed = database.get_processed_files();
+        let discovery = settings
+            .discovery()
+            .expect("Couldn't build import patterns");
+        let import_list = read_ocr_files(&discovery, &processed);
+        let category_list = database.get_categories();
+        let rules = database.get_rules();
+        let events = EventHandler::new();
+        // receipts synced into the import folder show up while the app is running
+        let import_watcher = events.watch(&discovery.roots, discovery.recursive).ok();
+        let classifier = classifier::Classifier::from_training_data(&database.get_training_data());
+        let summary_level = category_list
+            .iter()
+            .map(|category| category.depth)
+            .max()
+            .unwrap_or(0);
+        Self {
+            archive_path: settings.archive_path.clone(),
+            background_ocr: settings.background_ocr,
+            batch_list: BatchList {
+                items: Vec::new(),
+                pending: Vec::new(),
+                state: ListState::default(),
+            },
+            bon_history: History::default(),
+            bon_image: BonImage::default(),
+            bon_list: BonList {
+                items: bons,
+                state: ListState::default(),
+                status: None,
+            },
+            category_list: CategoryList {
+                items: category_list,
+                state: ListState::default(),
+                status: None,
+            },
+            bon_summary: Vec::new(),
+            budget_list: BudgetList {
+                items: Vec::new(),
+                month: String::new(),
+                state: ListState::default(),
+            },
+            category_source: 0,
+            chart_list: ChartList {
+                products: ProductHistory::new(),
+                reports: Vec::new(),
+                state: ListState::default(),
+                view: ChartView::Spending,
+            },
+            classifier,
+            current_state: AppState::Home,
+            database,
+            discovery,
+            duplicate_bon: None,
+            edit_field: TextArea::default(),
+            events,
+            export_path: settings.export_path.clone(),
+            import_list: FileList {
+                items: import_list,
+                state: ListState::default(),
+                warning: None,
+            },
+            _import_watcher: import_watcher,
+            new_bon_list: NewBonList {
+                completions: Vec::new(),
+                date: String::new(),
+                items: Vec::new(),
+                price_calc: 0.0,
+                price_eq: false,
+                price_ocr: 0.0,
+                state: ListState::default(),
+                store: String::new(),
+                warnings: Vec::new(),
+            },
+            ocr_blacklist: blacklist,
+            ocr_history: History::default(),
+            ocr_cache: HashMap::new(),
+            ocr_list: OcrList {
+                items: Vec::new(),
+                state: ListState::default(),
+                status: None,
+            },
+            ocr_file: String::new(),
+            ocr_scanning: Vec::new(),
+            post_import: settings.post_import,
+            price_list: PriceList {
+                alerts: Vec::new(),
+                products: ProductHistory::new(),
+            },
+            price_threshold: settings.price_threshold,
+            reconcile_list: ReconcileList {
+                bon_state: ListState::default(),
+                bons: Vec::new(),
+                focus: ReconcileFocus::Bons,
+                pair_state: ListState::default(),
+                pairs: Vec::new(),
+                status: None,
+                transaction_state: ListState::default(),
+                transactions: Vec::new(),
+            },
+            report_list: ReportList {
+                items: Vec::new(),
+                period: database::ReportPeriod::Month,
+                state: ListState::default(),
+            },
+            rule_is_regex: false,
+            rule_list: RuleList {
+                items: rules,
+                state: ListState::default(),
+                status: None,
+                updated: None,
+            },
+            rule_pattern: String::new(),
+            running: true,
+            statement_format: settings.statement_format,
+            summary_level,
         }
     }
 
@@ -848,13 +2705,201 @@ impl App<'_> {
     }
 }
 
-fn read_ocr_files(processed: &[String]) -> Vec<String> {
-    let settings = settings::Settings::new();
-    fs::read_dir(settings.import_path())
-        .expect("Couldn't read bons directory")
-        .filter_map(Result::ok)
-        .map(|entry| entry.file_name().to_string_lossy().into_owned())
-        .filter(|entry| !processed.iter().any(|elem| entry.contains(elem)))
-        .filter(|entry| entry.contains("jpg") || entry.contains("png") || entry.contains("jpeg"))
-        .collect::<Vec<String>>()
+// imports the ready entries, all of them or only the selected one, and returns the entries that
+// are left for review in their order
+fn approve_batch_entries(
+    items: Vec<BatchEntry>,
+    selected: Option<usize>,
+    all: bool,
+    mut import: impl FnMut(&mut BatchEntry) -> bool,
+) -> Vec<BatchEntry> {
+    items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, mut entry)| {
+            if entry.is_ready() && (all || Some(i) == selected) && import(&mut entry) {
+                None
+            } else {
+                Some(entry)
+            }
+        })
+        .collect()
+}
+
+fn read_ocr_files(
+    discovery: &discovery::Discovery,
+    processed: &[database::Processed],
+) -> Vec<discovery::ImportFile> {
+    discovery
+        .files()
+        .into_iter()
+        .filter(|file| !scan::is_processed(&file.path, processed))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(file: &str, price: f64) -> BatchEntry {
+        BatchEntry {
+            draft: scan::BonDraft {
+                date: "2024-12-01".to_string(),
+                items: vec![database::Entry::new("", "Milk", 2.0)],
+                price,
+                store: String::new(),
+            },
+            duplicate: None,
+            error: None,
+            file: file.to_string(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn convert_app() -> App<'static> {
+        let mut settings = settings::Settings::from_file("config/bon-scanner.toml");
+        settings.database_file = ":memory:".to_string();
+        settings.import_paths = Vec::new();
+        let mut app = App::with_settings(settings);
+        app.current_state = AppState::ConvertBon;
+        app.new_bon_list.items = vec![
+            database::Entry::new("", "Milk", 1.0),
+            database::Entry::new("", "Bread", 2.0),
+        ];
+        app.new_bon_list.state.select(Some(0));
+        app
+    }
+
+    fn products(app: &App) -> Vec<String> {
+        app.new_bon_list
+            .items
+            .iter()
+            .map(|entry| entry.product.clone())
+            .collect()
+    }
+
+    fn files(entries: &[BatchEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.file.as_str()).collect()
+    }
+
+    #[test]
+    fn batch_entry_ready() {
+        assert!(entry("a.jpg", 2.0).is_ready());
+        assert!(!entry("a.jpg", 20.0).is_ready());
+
+        let mut failed = entry("a.jpg", 2.0);
+        failed.error = Some("no text".to_string());
+        assert!(!failed.is_ready());
+
+        let mut duplicate = entry("a.jpg", 2.0);
+        duplicate.duplicate = Some("same as bon 1".to_string());
+        assert!(!duplicate.is_ready());
+
+        let mut undated = entry("a.jpg", 2.0);
+        undated.draft.date.clear();
+        assert!(!undated.is_ready());
+
+        let mut empty = entry("a.jpg", 0.0);
+        empty.draft.items.clear();
+        assert!(!empty.is_ready());
+    }
+
+    #[test]
+    fn approve_batch() {
+        let items = || {
+            vec![
+                entry("a.jpg", 2.0),
+                entry("b.jpg", 20.0),
+                entry("c.jpg", 2.0),
+            ]
+        };
+
+        let mut imported: Vec<String> = Vec::new();
+        let rest = approve_batch_entries(items(), Some(1), true, |entry| {
+            imported.push(entry.file.clone());
+            true
+        });
+        assert_eq!(vec!["a.jpg", "c.jpg"], imported);
+        assert_eq!(vec!["b.jpg"], files(&rest));
+
+        // without all only the selected entry is imported, and only when it is ready
+        let rest = approve_batch_entries(items(), Some(2), false, |_| true);
+        assert_eq!(vec!["a.jpg", "b.jpg"], files(&rest));
+        let rest = approve_batch_entries(items(), Some(1), false, |_| true);
+        assert_eq!(vec!["a.jpg", "b.jpg", "c.jpg"], files(&rest));
+
+        // entries that can't be imported stay in their place
+        let rest = approve_batch_entries(items(), None, true, |entry| entry.file != "a.jpg");
+        assert_eq!(vec!["a.jpg", "b.jpg"], files(&rest));
+    }
+
+    #[tokio::test]
+    async fn duplicate_entry() {
+        let mut app = convert_app();
+        app.handle_key_events(KeyEvent::from(KeyCode::Char('D')))
+            .expect("Couldn't handle key");
+        assert_eq!(vec!["Milk", "Milk", "Bread"], products(&app));
+        assert_eq!(Some(1), app.new_bon_list.state.selected());
+
+        app.undo();
+        assert_eq!(vec!["Milk", "Bread"], products(&app));
+    }
+
+    #[tokio::test]
+    async fn move_entry() {
+        let mut app = convert_app();
+        // moving past either end changes nothing and records nothing
+        app.move_entry(false);
+        app.new_bon_list.state.select(Some(1));
+        app.move_entry(true);
+        assert_eq!(vec!["Milk", "Bread"], products(&app));
+        assert!(!app.bon_history.undo(&mut app.new_bon_list));
+
+        app.move_entry(false);
+        assert_eq!(vec!["Bread", "Milk"], products(&app));
+        assert_eq!(Some(0), app.new_bon_list.state.selected());
+        app.move_entry(true);
+        assert_eq!(vec!["Milk", "Bread"], products(&app));
+        assert_eq!(Some(1), app.new_bon_list.state.selected());
+    }
+
+    #[tokio::test]
+    async fn edit_price_total() {
+        let mut app = convert_app();
+        app.new_bon_list.state.select(Some(1));
+        app.current_state = AppState::EditPrice;
+        app.edit_field.insert_str("3,5");
+        app.calculate_summary();
+        assert_eq!(4.5, app.new_bon_list.price_calc);
+
+        // until the typed text is a price the stored one counts
+        app.edit_field.insert_str("x");
+        app.calculate_summary();
+        assert_eq!(3.0, app.new_bon_list.price_calc);
+    }
+
+    #[tokio::test]
+    async fn unchanged_header() {
+        let mut app = convert_app();
+        app.new_bon_list.store = "Corner Shop".to_string();
+        app.edit_bon_header(|header| header.store = "Corner Shop".to_string());
+        assert!(!app.bon_history.undo(&mut app.new_bon_list));
+
+        app.edit_bon_header(|header| header.store = "Bakery".to_string());
+        assert!(app.bon_history.undo(&mut app.new_bon_list));
+        assert_eq!("Corner Shop", app.new_bon_list.store);
+
+        app.ocr_list.items = vec![OcrEntry {
+            name: "Milk 1,49".to_string(),
+            ocr_type: OcrType::Entry,
+            region: None,
+        }];
+        app.ocr_list.state.select_first();
+        app.edit_ocr_line(|line| line.name = "Milk 1,49".to_string());
+        assert!(!app.ocr_history.undo(&mut app.ocr_list));
+
+        app.edit_ocr_line(|line| line.ocr_type = OcrType::Sum);
+        assert!(app.ocr_history.undo(&mut app.ocr_list));
+        assert_eq!(OcrType::Entry, app.ocr_list.items[0].ocr_type);
+    }
 }
@@ -0,0 +1,364 @@
+use crate::database::Database;
+use color_eyre::eyre::{OptionExt, eyre};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const BACKUP_VERSION: i64 = 2;
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Backup {
+    pub version: i64,
+    pub bons: Vec<BonRecord>,
+    pub entries: Vec<EntryRecord>,
+    pub products: Vec<ProductRecord>,
+    pub categories: Vec<CategoryRecord>,
+    pub blacklist: Vec<String>,
+    pub processed: Vec<ProcessedRecord>,
+    #[serde(default)]
+    pub rules: Vec<RuleRecord>,
+    #[serde(default)]
+    pub budgets: Vec<BudgetRecord>,
+    #[serde(default)]
+    pub transactions: Vec<TransactionRecord>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct BonRecord {
+    pub bon_id: i64,
+    pub date: String,
+    pub price: f64,
+    pub store: String,
+    pub hidden: bool,
+    pub hide_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct BudgetRecord {
+    pub category_id: i64,
+    pub monthly_limit: f64,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct CategoryRecord {
+    pub category_id: i64,
+    pub category: String,
+    pub color: Option<String>,
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct EntryRecord {
+    pub entry_id: i64,
+    pub bon_id: i64,
+    pub product_id: i64,
+    pub price: f64,
+}
+
+// version 1 backups only stored the file names
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "ProcessedValue")]
+pub struct ProcessedRecord {
+    pub entry: String,
+    pub path: String,
+    pub hash: String,
+    pub bon_id: Option<i64>,
+    pub archive_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProcessedValue {
+    Entry(String),
+    Record {
+        entry: String,
+        path: String,
+        hash: String,
+        bon_id: Option<i64>,
+        #[serde(default)]
+        archive_path: Option<String>,
+    },
+}
+
+impl From<ProcessedValue> for ProcessedRecord {
+    fn from(value: ProcessedValue) -> Self {
+        match value {
+            ProcessedValue::Entry(entry) => Self {
+                entry,
+                path: String::new(),
+                hash: String::new(),
+                bon_id: None,
+                archive_path: None,
+            },
+            ProcessedValue::Record {
+                entry,
+                path,
+                hash,
+                bon_id,
+                archive_path,
+            } => Self {
+                entry,
+                path,
+                hash,
+                bon_id,
+                archive_path,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProductRecord {
+    pub product_id: i64,
+    pub category_id: i64,
+    pub product: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct RuleRecord {
+    pub rule_id: i64,
+    pub pattern: String,
+    pub is_regex: bool,
+    pub category_id: i64,
+    pub priority: i64,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct TransactionRecord {
+    pub transaction_id: i64,
+    pub date: String,
+    pub amount: f64,
+    pub description: String,
+    pub bon_id: Option<i64>,
+}
+
+pub fn export(database: &Database, file: &Path) -> color_eyre::Result<()> {
+    let json = serde_json::to_string_pretty(&database.get_backup())?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file, json)?;
+    Ok(())
+}
+
+pub fn import(database: &Database, file: &Path, merge: bool) -> color_eyre::Result<()> {
+    let backup = parse(&fs::read_to_string(file)?)?;
+    if merge {
+        database.merge_backup(&backup)?;
+    } else if database.is_empty() {
+        database.restore_backup(&backup)?;
+    } else {
+        return Err(eyre!(
+            "Database isn't empty, use --merge to import into an existing database"
+        ));
+    }
+    Ok(())
+}
+
+fn parse(json: &str) -> color_eyre::Result<Backup> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let version = value
+        .get("version")
+        .and_then(|version| version.as_i64())
+        .ok_or_eyre("Backup has no version")?;
+    if version > BACKUP_VERSION {
+        return Err(eyre!(
+            "Backup version {version} is newer than the supported version {BACKUP_VERSION}"
+        ));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Database {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("food");
+        database.create_category("dairy");
+        database.set_category_parent(2, Some(1));
+        database.set_category_color(1, Some("green"));
+        database.create_product(1, "Bread");
+        database.create_product(2, "Milk");
+        database.create_bon("2024-12-01", 5.0);
+        database.create_bon("2025-01-24", 2.0);
+        database.set_bon_store(1, "Corner Shop");
+        database.hide_bon(2);
+        database.create_entry(1, 1, 3.0);
+        database.create_entry(1, 2, 2.0);
+        database.create_entry(2, 2, 2.0);
+        database.add_blacklist_entry("Total");
+        database.add_processed_entry("bon.jpg");
+        database.add_processed_file("IMG_1.jpg", "/inbox/IMG_1.jpg", "abc", 1);
+        database.set_processed_archive(1, "/archive/IMG_1.jpg");
+        database
+            .create_rule("milk", false, 2, 1)
+            .expect("Couldn't create rule");
+        database.set_budget(1, 100.0);
+        database.create_transaction("2024-12-02", -5.0, "Corner Shop's card payment");
+        database.pair_transaction(1, Some(1));
+        database
+    }
+
+    #[test]
+    fn roundtrip() {
+        let database = database();
+        let backup = database.get_backup();
+        assert_eq!(BACKUP_VERSION, backup.version);
+        assert_eq!(2, backup.bons.len());
+        assert!(backup.bons[1].hidden);
+        assert_eq!(3, backup.entries.len());
+
+        let json = serde_json::to_string(&backup).expect("Couldn't serialize backup");
+        let parsed = parse(&json).expect("Couldn't parse backup");
+        assert_eq!(backup, parsed);
+
+        let restored = Database::new(":memory:");
+        restored.create_database();
+        assert!(restored.is_empty());
+        restored
+            .restore_backup(&parsed)
+            .expect("Couldn't restore backup");
+        assert!(!restored.is_empty());
+        assert_eq!(backup, restored.get_backup());
+    }
+
+    #[test]
+    fn merge() {
+        let backup = database().get_backup();
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("household");
+        database.create_category("food");
+        database.create_product(1, "Soap");
+        database.create_bon("2024-11-11", 1.0);
+        database.create_entry(1, 1, 1.0);
+        database.add_blacklist_entry("Total");
+
+        database
+            .merge_backup(&backup)
+            .expect("Couldn't merge backup");
+        let merged = database.get_backup();
+        assert_eq!(3, merged.bons.len());
+        assert_eq!(4, merged.entries.len());
+        assert_eq!(vec!["Total".to_string()], merged.blacklist);
+        let categories: Vec<(&str, Option<i64>)> = merged
+            .categories
+            .iter()
+            .map(|category| (category.category.as_str(), category.parent_id))
+            .collect();
+        assert_eq!(
+            vec![("household", None), ("food", None), ("dairy", Some(2))],
+            categories
+        );
+        let milk = merged
+            .products
+            .iter()
+            .find(|product| product.product == "Milk")
+            .expect("Product is missing");
+        assert_eq!(3, milk.category_id);
+        assert_eq!(3, merged.rules[0].category_id);
+        assert_eq!(2, merged.budgets[0].category_id);
+        let corner_shop = merged
+            .bons
+            .iter()
+            .find(|bon| bon.store == "Corner Shop")
+            .expect("Bon is missing");
+        let entries: Vec<i64> = merged
+            .entries
+            .iter()
+            .filter(|entry| entry.bon_id == corner_shop.bon_id)
+            .map(|entry| entry.product_id)
+            .collect();
+        assert_eq!(vec![2, milk.product_id], entries);
+        assert_eq!(Some(corner_shop.bon_id), merged.transactions[0].bon_id);
+        assert_eq!(Some(corner_shop.bon_id), merged.processed[1].bon_id);
+
+        // merging the same backup again doesn't duplicate anything
+        database
+            .merge_backup(&backup)
+            .expect("Couldn't merge backup");
+        assert_eq!(merged, database.get_backup());
+    }
+
+    #[test]
+    fn merge_same_price() {
+        let backup = database().get_backup();
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("food");
+        database.create_product(1, "Bread");
+        database.create_bon("2024-12-01", 5.0);
+        database.set_bon_store(1, "Corner Shop");
+        database.create_entry(1, 1, 5.0);
+
+        // same day, store and total, but different entries
+        database
+            .merge_backup(&backup)
+            .expect("Couldn't merge backup");
+        let merged = database.get_backup();
+        assert_eq!(3, merged.bons.len());
+        assert_eq!(4, merged.entries.len());
+    }
+
+    #[test]
+    fn merge_rollback() {
+        let mut backup = database().get_backup();
+        backup.entries[0].product_id = 99;
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("household");
+        let before = database.get_backup();
+
+        assert!(database.merge_backup(&backup).is_err());
+        assert_eq!(before, database.get_backup());
+
+        let restored = Database::new(":memory:");
+        restored.create_database();
+        // a second entry with the same id makes the restore fail halfway
+        backup.entries.push(EntryRecord {
+            entry_id: 1,
+            bon_id: 1,
+            product_id: 1,
+            price: 1.0,
+        });
+        assert!(restored.restore_backup(&backup).is_err());
+        assert!(restored.is_empty());
+    }
+
+    #[test]
+    fn version() {
+        assert!(parse("{}").is_err());
+        assert!(parse("{\"version\": 99}").is_err());
+        let json = format!(
+            "{{\"version\": {BACKUP_VERSION}, \"bons\": [], \"entries\": [], \"products\": [], \
+             \"categories\": [], \"blacklist\": [], \"processed\": []}}"
+        );
+        assert_eq!(
+            Backup {
+                version: BACKUP_VERSION,
+                ..Default::default()
+            },
+            parse(&json).expect("Couldn't parse backup")
+        );
+    }
+
+    #[test]
+    fn version_1_processed() {
+        let json = "{\"version\": 1, \"bons\": [], \"entries\": [], \"products\": [], \
+                    \"categories\": [], \"blacklist\": [], \"processed\": [\"bon.jpg\"]}";
+        let backup = parse(json).expect("Couldn't parse backup");
+        assert_eq!(
+            vec![ProcessedRecord {
+                entry: "bon.jpg".to_string(),
+                path: String::new(),
+                hash: String::new(),
+                bon_id: None,
+                archive_path: None,
+            }],
+            backup.processed
+        );
+    }
+}
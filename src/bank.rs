@@ -0,0 +1,300 @@
+use crate::database::{Bon, Database, Transaction, day};
+use std::fs;
+use std::path::Path;
+
+const MAX_DAYS_APART: i64 = 3;
+
+pub struct StatementFormat {
+    pub amount_column: usize,
+    pub date_column: usize,
+    pub delimiter: char,
+    pub description_column: usize,
+    pub skip_rows: usize,
+}
+
+impl Default for StatementFormat {
+    fn default() -> Self {
+        Self {
+            amount_column: 1,
+            date_column: 0,
+            delimiter: ';',
+            description_column: 2,
+            skip_rows: 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StatementLine {
+    pub amount: f64,
+    pub date: String,
+    pub description: String,
+}
+
+pub fn auto_pair(bons: &[Bon], transactions: &[Transaction]) -> Vec<(i64, i64)> {
+    let paired_bons: Vec<i64> = transactions
+        .iter()
+        .filter_map(|transaction| transaction.bon_id)
+        .collect();
+    let mut candidates: Vec<(i64, i64, i64)> = Vec::new();
+    for bon in bons.iter().filter(|bon| !paired_bons.contains(&bon.bon_id)) {
+        for transaction in transactions
+            .iter()
+            .filter(|transaction| transaction.bon_id.is_none())
+        {
+            // bons are paid, so refunds and other credits never pair with one
+            if transaction.amount >= 0.0 || (-transaction.amount - bon.price).abs() >= 0.005 {
+                continue;
+            }
+            if let Some(days) = days_apart(&bon.date, &transaction.date)
+                && days <= MAX_DAYS_APART
+            {
+                candidates.push((days, bon.bon_id, transaction.transaction_id));
+            }
+        }
+    }
+    // pair the closest dates first, every bon and transaction is used only once
+    candidates.sort();
+    let mut pairs: Vec<(i64, i64)> = Vec::new();
+    for (_, bon_id, transaction_id) in candidates {
+        if !pairs
+            .iter()
+            .any(|(bon, transaction)| *bon == bon_id || *transaction == transaction_id)
+        {
+            pairs.push((bon_id, transaction_id));
+        }
+    }
+    pairs
+}
+
+fn days_apart(a: &str, b: &str) -> Option<i64> {
+    Some((days_from_civil(a)? - days_from_civil(b)?).abs())
+}
+
+// days since 1970-01-01 for a yyyy-mm-dd date
+fn days_from_civil(date: &str) -> Option<i64> {
+    let mut split = day(date).split('-');
+    let year = split.next()?.parse::<i64>().ok()?;
+    let month = split.next()?.parse::<i64>().ok()?;
+    let day = split.next()?.parse::<i64>().ok()?;
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+pub fn import_statement(
+    database: &Database,
+    file: &Path,
+    format: &StatementFormat,
+) -> std::io::Result<usize> {
+    let lines = parse_statement(&fs::read_to_string(file)?, format);
+    let existing = database.get_transactions();
+    let mut imported = 0;
+    for line in lines {
+        let exists = existing.iter().any(|transaction| {
+            transaction.date == line.date
+                && transaction.amount == line.amount
+                && transaction.description == line.description
+        });
+        if !exists {
+            database.create_transaction(&line.date, line.amount, &line.description);
+            imported += 1;
+        }
+    }
+    Ok(imported)
+}
+
+fn normalize_date(date: &str) -> Option<String> {
+    let date = date.trim();
+    let split: Vec<&str> = date.split(['.', '-', '/']).collect();
+    if split.len() != 3 || split.iter().any(|part| part.parse::<u32>().is_err()) {
+        return None;
+    }
+    if split[0].len() == 4 {
+        Some(format!("{}-{:0>2}-{:0>2}", split[0], split[1], split[2]))
+    } else {
+        let year = if split[2].len() == 2 {
+            format!("20{}", split[2])
+        } else {
+            split[2].to_string()
+        };
+        Some(format!("{}-{:0>2}-{:0>2}", year, split[1], split[0]))
+    }
+}
+
+pub fn pair(database: &Database) -> usize {
+    let pairs = auto_pair(&database.get_bons(), &database.get_transactions());
+    pairs.iter().for_each(|(bon_id, transaction_id)| {
+        database.pair_transaction(*transaction_id, Some(*bon_id))
+    });
+    pairs.len()
+}
+
+// statements use either a decimal comma or a decimal point, so the last separator is the
+// decimal one unless it repeats like in 1.234.567, all others group thousands
+fn parse_amount(amount: &str) -> Option<f64> {
+    let amount: String = amount
+        .trim()
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '€')
+        .collect();
+    let decimal = amount
+        .chars()
+        .rfind(|c| matches!(c, '.' | ','))
+        .filter(|separator| amount.matches(*separator).count() == 1);
+    let amount: String = amount
+        .chars()
+        .filter(|c| !matches!(c, '.' | ',') || Some(*c) == decimal)
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+    amount.parse::<f64>().ok()
+}
+
+fn parse_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == delimiter {
+            fields.push(field.clone());
+            field.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+pub fn parse_statement(content: &str, format: &StatementFormat) -> Vec<StatementLine> {
+    content
+        .lines()
+        .skip(format.skip_rows)
+        .filter_map(|line| {
+            let fields = parse_fields(line.trim_end_matches('\r'), format.delimiter);
+            let date = normalize_date(fields.get(format.date_column)?)?;
+            let amount = parse_amount(fields.get(format.amount_column)?)?;
+            let description = fields
+                .get(format.description_column)
+                .map(|description| description.trim().to_string())
+                .unwrap_or_default();
+            Some(StatementLine {
+                amount,
+                date,
+                description,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts() {
+        assert_eq!(Some(-12.34), parse_amount("-12,34"));
+        assert_eq!(Some(-1234.5), parse_amount("-1.234,50 €"));
+        assert_eq!(Some(12.34), parse_amount("12.34"));
+        assert_eq!(Some(1234.5), parse_amount("1,234.50"));
+        assert_eq!(Some(1234567.0), parse_amount("1.234.567"));
+        assert_eq!(None, parse_amount("n/a"));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(Some("2024-12-01".to_string()), normalize_date("01.12.2024"));
+        assert_eq!(Some("2024-12-01".to_string()), normalize_date("1.12.24"));
+        assert_eq!(Some("2024-12-01".to_string()), normalize_date("2024-12-01"));
+        assert_eq!(None, normalize_date("Buchungstag"));
+        assert_eq!(Some(0), days_from_civil("1970-01-01"));
+        assert_eq!(Some(3), days_apart("2024-02-27", "2024-03-01"));
+        assert_eq!(Some(2), days_apart("2025-01-01", "2024-12-30"));
+        assert_eq!(
+            Some(0),
+            days_apart("2024-12-24 12:12:12 +0100", "2024-12-24")
+        );
+    }
+
+    #[test]
+    fn statement() {
+        let content = "Buchungstag;Betrag;Verwendungszweck\r\n\
+                       01.12.2024;-12,34;\"Corner Shop; Card 1234\"\r\n\
+                       02.12.2024;1.500,00;Salary\r\n\
+                       invalid line\r\n";
+        let lines = parse_statement(content, &StatementFormat::default());
+        assert_eq!(
+            vec![
+                StatementLine {
+                    amount: -12.34,
+                    date: "2024-12-01".to_string(),
+                    description: "Corner Shop; Card 1234".to_string(),
+                },
+                StatementLine {
+                    amount: 1500.0,
+                    date: "2024-12-02".to_string(),
+                    description: "Salary".to_string(),
+                },
+            ],
+            lines
+        );
+
+        let format = StatementFormat {
+            amount_column: 2,
+            date_column: 1,
+            delimiter: ',',
+            description_column: 0,
+            skip_rows: 0,
+        };
+        let lines = parse_statement("\"Shop, Inc\",2024-12-01,-3.50", &format);
+        assert_eq!(-3.5, lines[0].amount);
+        assert_eq!("Shop, Inc", lines[0].description);
+    }
+
+    #[test]
+    fn pairing() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-12-01", 12.34);
+        database.create_bon("2024-12-05", 12.34);
+        database.create_bon("2024-12-10", 5.0);
+        database.create_bon("2024-12-20", 7.0);
+        database.create_bon("2024-12-24 12:12:12 +0100", 3.0);
+        database.create_transaction("2024-12-06", -12.34, "Corner Shop");
+        database.create_transaction("2024-12-02", -12.34, "Corner Shop");
+        database.create_transaction("2024-12-15", -5.0, "too late");
+        database.create_transaction("2024-12-20", -7.5, "other amount");
+        database.create_transaction("2024-12-21", 7.0, "refund");
+        database.create_transaction("2024-12-24", -3.0, "Bakery");
+
+        let pairs = auto_pair(&database.get_bons(), &database.get_transactions());
+        assert_eq!(vec![(5, 6), (1, 2), (2, 1)], pairs);
+
+        database.pair_transaction(1, Some(4));
+        assert_eq!(2, pair(&database));
+        let transactions = database.get_transactions();
+        assert_eq!(Some(4), transactions[0].bon_id);
+        assert_eq!(Some(1), transactions[1].bon_id);
+        assert_eq!(None, transactions[2].bon_id);
+        assert_eq!(None, transactions[4].bon_id);
+        assert_eq!(Some(5), transactions[5].bon_id);
+        assert_eq!(0, pair(&database));
+    }
+}
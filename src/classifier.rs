@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+pub struct Classifier {
+    categories: HashMap<String, Counts>,
+    documents: usize,
+    vocabulary: HashSet<String>,
+}
+
+#[derive(Default)]
+struct Counts {
+    documents: usize,
+    tokens: HashMap<String, usize>,
+    total_tokens: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub category: String,
+    pub confidence: f64,
+}
+
+impl Classifier {
+    // categories are learned by name, so the model is rebuilt whenever they are renamed,
+    // merged or deleted instead of being patched
+    pub fn from_training_data(training_data: &[(String, String)]) -> Self {
+        let mut classifier = Self::new();
+        training_data
+            .iter()
+            .for_each(|(product, category)| classifier.train(product, category));
+        classifier
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn suggest(&self, product: &str) -> Option<Suggestion> {
+        let tokens = tokenize(product);
+        if self.documents == 0 || tokens.is_empty() {
+            return None;
+        }
+        let vocabulary = self.vocabulary.len() as f64;
+        let scores: Vec<(&String, f64)> = self
+            .categories
+            .iter()
+            .map(|(category, counts)| {
+                let prior = (counts.documents as f64 / self.documents as f64).ln();
+                let likelihood: f64 = tokens
+                    .iter()
+                    .map(|token| {
+                        let count = counts.tokens.get(token).copied().unwrap_or(0) as f64;
+                        ((count + 1.0) / (counts.total_tokens as f64 + vocabulary)).ln()
+                    })
+                    .sum();
+                (category, prior + likelihood)
+            })
+            .collect();
+        // convert the log probabilities into a normalised confidence
+        let max_score = scores
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let total: f64 = scores
+            .iter()
+            .map(|(_, score)| (score - max_score).exp())
+            .sum();
+        scores
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(category, score)| Suggestion {
+                category: category.to_string(),
+                confidence: (score - max_score).exp() / total,
+            })
+    }
+
+    pub fn train(&mut self, product: &str, category: &str) {
+        if category.is_empty() {
+            return;
+        }
+        let tokens = tokenize(product);
+        let counts = self.categories.entry(category.to_string()).or_default();
+        counts.documents += 1;
+        counts.total_tokens += tokens.len();
+        for token in tokens {
+            *counts.tokens.entry(token.clone()).or_insert(0) += 1;
+            self.vocabulary.insert(token);
+        }
+        self.documents += 1;
+    }
+}
+
+// receipts abbreviate product names a lot, so besides the words themselves
+// the character trigrams of every word are used as features
+fn tokenize(product: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    product
+        .to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| word.chars().count() > 1)
+        .for_each(|word| {
+            tokens.push(word.to_string());
+            let chars: Vec<char> = word.chars().collect();
+            chars.windows(3).for_each(|trigram| {
+                tokens.push(format!("#{}", trigram.iter().collect::<String>()))
+            });
+        });
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trained() -> Classifier {
+        let mut classifier = Classifier::new();
+        classifier.train("Weidemilch 3,5%", "dairy");
+        classifier.train("H-Milch 1,5%", "dairy");
+        classifier.train("Butter", "dairy");
+        classifier.train("Gouda jung", "dairy");
+        classifier.train("Spuelmittel", "cleaning");
+        classifier.train("Allzweckreiniger", "cleaning");
+        classifier.train("Glasreiniger", "cleaning");
+        classifier
+    }
+
+    #[test]
+    fn empty() {
+        let classifier = Classifier::new();
+        assert_eq!(None, classifier.suggest("Milch"));
+    }
+
+    #[test]
+    fn suggest() {
+        let classifier = trained();
+        let suggestion = classifier.suggest("Bio Milch").expect("No suggestion");
+        assert_eq!("dairy", suggestion.category);
+        assert!(suggestion.confidence > 0.5);
+
+        let suggestion = classifier.suggest("Badreiniger").expect("No suggestion");
+        assert_eq!("cleaning", suggestion.category);
+        assert!(suggestion.confidence > 0.5);
+        assert!(suggestion.confidence <= 1.0);
+
+        assert_eq!(None, classifier.suggest("1,99"));
+    }
+
+    #[test]
+    fn train_incrementally() {
+        let mut classifier = trained();
+        let before = classifier.suggest("Apfel").expect("No suggestion");
+        classifier.train("Apfel Elstar", "fruit");
+        classifier.train("Apfel Braeburn", "fruit");
+        let after = classifier.suggest("Apfel").expect("No suggestion");
+        assert_ne!(before.category, after.category);
+        assert_eq!("fruit", after.category);
+    }
+
+    #[test]
+    fn ignore_empty_category() {
+        let mut classifier = Classifier::new();
+        classifier.train("Milch", "");
+        assert_eq!(None, classifier.suggest("Milch"));
+    }
+
+    #[test]
+    fn retrain() {
+        let training_data = vec![
+            ("Weidemilch 3,5%".to_string(), "dairy".to_string()),
+            ("Spuelmittel".to_string(), "cleaning".to_string()),
+        ];
+        let classifier = Classifier::from_training_data(&training_data);
+        let suggestion = classifier.suggest("Milch").expect("No suggestion");
+        assert_eq!("dairy", suggestion.category);
+
+        // after dairy was renamed the old name is never suggested again
+        let training_data = vec![
+            ("Weidemilch 3,5%".to_string(), "milk".to_string()),
+            ("Spuelmittel".to_string(), "cleaning".to_string()),
+        ];
+        let classifier = Classifier::from_training_data(&training_data);
+        let suggestion = classifier.suggest("Milch").expect("No suggestion");
+        assert_eq!("milk", suggestion.category);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::backup;
+use crate::bank;
+use crate::database::{Database, ReportPeriod};
+use crate::export::{self, Dialect, ExportFilter};
+use crate::scan::{self, BonDraft, OcrType};
+use crate::settings::Settings;
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{OptionExt, eyre};
+use std::path::Path;
+
+#[derive(Parser)]
+#[command(version, about = "Scan, categorise and report shopping receipts")]
+pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        help = "Settings file to use instead of the default one"
+    )]
+    pub config: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Database file, overrides the settings file"
+    )]
+    pub database: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Export the database to a JSON backup")]
+    Backup { file: String },
+    #[command(about = "Import a bank statement and pair transactions with bons")]
+    Bank { file: String },
+    #[command(about = "Export bons as CSV or as ledger, hledger or beancount journal")]
+    Export {
+        #[arg(long, help = "Funding account for journal exports")]
+        account: Option<String>,
+        #[arg(long)]
+        category: Option<String>,
+        #[arg(long, default_value = "csv")]
+        format: String,
+        #[arg(long, help = "First date to export, yyyy-mm-dd")]
+        from: Option<String>,
+        #[arg(long, help = "Output directory, defaults to the export path")]
+        output: Option<String>,
+        #[arg(long, help = "Last date to export, yyyy-mm-dd")]
+        to: Option<String>,
+    },
+    #[command(about = "Scan a bon and import it with the detected date, sum and categories")]
+    Import {
+        file: String,
+        #[arg(
+            long,
+            help = "Import even if the file or a bon like it was imported before"
+        )]
+        force: bool,
+        #[arg(
+            long,
+            help = "Import without asking, otherwise only the detected bon is printed"
+        )]
+        yes: bool,
+    },
+    #[command(about = "Create the database or update it to the latest version")]
+    InitDb,
+    #[command(about = "List all bons")]
+    List,
+    #[command(about = "Print spending per month or year")]
+    Report {
+        #[arg(long)]
+        year: bool,
+    },
+    #[command(about = "Import a JSON backup")]
+    Restore {
+        file: String,
+        #[arg(long, help = "Merge into an existing database")]
+        merge: bool,
+    },
+    #[command(about = "Run OCR on a bon and print the recognised lines")]
+    Scan { file: String },
+}
+
+impl Cli {
+    // only the default settings file is optional, a missing --config is most likely a typo
+    pub fn settings(&self) -> color_eyre::Result<Settings> {
+        let mut settings = match &self.config {
+            Some(config) if !Path::new(config).exists() => {
+                return Err(eyre!("Settings file {config} doesn't exist"));
+            }
+            Some(config) => Settings::from_file(config),
+            None => Settings::new(),
+        };
+        if let Some(database) = &self.database {
+            settings.database_file = database.clone();
+        }
+        // a broken import pattern would otherwise just hide files
+        settings.discovery()?;
+        Ok(settings)
+    }
+}
+
+pub fn run(command: Command, settings: &Settings) -> color_eyre::Result<()> {
+    match command {
+        Command::Backup { file } => {
+            let database = existing_database(settings)?;
+            backup::export(&database, Path::new(&file))?;
+            println!("Exported backup to {file}");
+        }
+        Command::Bank { file } => {
+            let database = existing_database(settings)?;
+            let imported =
+                bank::import_statement(&database, Path::new(&file), &settings.statement_format)?;
+            let paired = bank::pair(&database);
+            println!("Imported {imported} transactions, paired {paired} with bons");
+        }
+        Command::Export {
+            account,
+            category,
+            format,
+            from,
+            output,
+            to,
+        } => {
+            let database = existing_database(settings)?;
+            let filter = ExportFilter { category, from, to };
+            let output = output.unwrap_or_else(|| settings.export_path.clone());
+            if format == "csv" {
+                export::write_csv(&database, Path::new(&output), &filter)?;
+                println!("Exported bons.csv and entries.csv to {output}");
+            } else {
+                let dialect = Dialect::parse(&format)
+                    .ok_or_eyre(format!("Unknown export format {format}"))?;
+                let file = Path::new(&output).join(format!("bons.{}", dialect.extension()));
+                let account = account.unwrap_or_else(|| settings.funding_account.clone());
+                export::write_journal(&database, &file, &filter, dialect, &account)?;
+                println!("Exported {}", file.display());
+            }
+        }
+        Command::Import { file, force, yes } => import(settings, &file, yes, force)?,
+        Command::InitDb => {
+            if settings.database_exists() {
+                Database::new(&settings.database_file).update_database();
+                println!("Updated database {}", settings.database_file);
+            } else {
+                Database::new(&settings.database_file).create_database();
+                println!("Created database {}", settings.database_file);
+            }
+        }
+        Command::List => {
+            let database = existing_database(settings)?;
+            database.get_bons().iter().for_each(|bon| {
+                println!(
+                    "{:>5}  {}  {:>8.2}  {}",
+                    bon.bon_id, bon.date, bon.price, bon.store
+                )
+            });
+        }
+        Command::Report { year } => {
+            let database = existing_database(settings)?;
+            let period = if year {
+                ReportPeriod::Year
+            } else {
+                ReportPeriod::Month
+            };
+            database.get_report(period).iter().for_each(|report| {
+                let change = report
+                    .change
+                    .map(|change| format!("  {change:+.1}%"))
+                    .unwrap_or_default();
+                println!("{}  {:>8.2}{}", report.period, report.total, change);
+                report.categories.iter().for_each(|(category, total)| {
+                    println!("    {category:<20} {total:>8.2}");
+                });
+            });
+        }
+        Command::Restore { file, merge } => {
+            let database = existing_database(settings)?;
+            backup::import(&database, Path::new(&file), merge)?;
+            println!("Imported backup from {file}");
+        }
+        Command::Scan { file } => {
+            let database = existing_database(settings)?;
+            let mut entries = scan::ocr(&file, &database.get_blacklist())?;
+            scan::detect(&mut entries);
+            entries.iter().for_each(|entry| {
+                let prefix = match entry.ocr_type {
+                    OcrType::Date => "D: ",
+                    OcrType::Entry => "",
+                    OcrType::Sum => "S: ",
+                };
+                println!("{prefix}{}", entry.name);
+            });
+        }
+    }
+    Ok(())
+}
+
+fn existing_database(settings: &Settings) -> color_eyre::Result<Database> {
+    if !settings.database_exists() {
+        return Err(eyre!(
+            "Database {} doesn't exist, create it with init-db",
+            settings.database_file
+        ));
+    }
+    let database = Database::new(&settings.database_file);
+    database.update_database();
+    Ok(database)
+}
+
+fn import(settings: &Settings, file: &str, yes: bool, force: bool) -> color_eyre::Result<()> {
+    let database = existing_database(settings)?;
+    let mut entries = scan::ocr(file, &database.get_blacklist())?;
+    scan::detect(&mut entries);
+    let draft = scan::convert(&database, &entries);
+    if draft.date.is_empty() {
+        return Err(eyre!("Couldn't detect a date in {file}"));
+    }
+    println!("{}  {:.2}", draft.date, draft.price);
+    draft.items.iter().for_each(|entry| {
+        println!(
+            "    {:<30} {:<20} {:>8.2}",
+            entry.product, entry.category, entry.price
+        )
+    });
+    if !draft.price_matches() {
+        println!(
+            "Warning: the entries add up to {:.2}, the bon sum is {:.2}",
+            draft.items_total(),
+            draft.price
+        );
+    }
+    if yes {
+        import_draft(settings, &database, &draft, file, force)?;
+    } else {
+        if let Some(previous) = scan::previous_import(&database, &draft, Path::new(file)) {
+            println!("Warning: {file} is {previous}");
+        }
+        println!("Run again with --yes to import this bon");
+    }
+    Ok(())
+}
+
+fn import_draft(
+    settings: &Settings,
+    database: &Database,
+    draft: &BonDraft,
+    file: &str,
+    force: bool,
+) -> color_eyre::Result<()> {
+    if !force && let Some(previous) = scan::previous_import(database, draft, Path::new(file)) {
+        return Err(eyre!(
+            "Not importing {file}, it is {previous}. Run again with --force to import it anyway"
+        ));
+    }
+    let bon_id = scan::import(database, draft, file);
+    println!("Imported bon {bon_id}");
+    match scan::archive(
+        database,
+        bon_id,
+        draft,
+        file,
+        settings.post_import,
+        &settings.archive_path,
+    )? {
+        Some(target) if target != Path::new(file) => {
+            println!("Moved {file} to {}", target.display())
+        }
+        Some(_) => {}
+        None => println!("Deleted {file}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Entry;
+    use crate::scan::PostImport;
+    use std::fs;
+
+    #[test]
+    fn import_twice() {
+        let root = std::env::temp_dir().join(format!("bon-scanner-cli-{}", std::process::id()));
+        fs::create_dir_all(&root).expect("Couldn't create directory");
+        let mut settings = Settings::from_file("config/bon-scanner.toml");
+        settings.database_file = root.join("bons.sqlite").to_string_lossy().into_owned();
+        settings.post_import = PostImport::Keep;
+        let database = Database::new(&settings.database_file);
+        database.create_database();
+        let draft = BonDraft {
+            date: "24.12.2024".to_string(),
+            items: vec![Entry::new("Food", "Milk", 1.49)],
+            price: 1.49,
+            store: String::new(),
+        };
+        let file = root.join("IMG_1.jpg");
+        fs::write(&file, "bon").expect("Couldn't write file");
+        let file = file.to_string_lossy().into_owned();
+        let copy = root.join("copy.jpg");
+        fs::write(&copy, "bon").expect("Couldn't write file");
+        let copy = copy.to_string_lossy().into_owned();
+        let other = root.join("IMG_2.jpg");
+        fs::write(&other, "other photo").expect("Couldn't write file");
+        let other = other.to_string_lossy().into_owned();
+
+        assert!(import_draft(&settings, &database, &draft, &file, false).is_ok());
+        assert!(import_draft(&settings, &database, &draft, &file, false).is_err());
+        // the same photo under another name
+        assert!(import_draft(&settings, &database, &draft, &copy, false).is_err());
+        // another photo of the same bon
+        assert!(import_draft(&settings, &database, &draft, &other, false).is_err());
+        assert_eq!(1, database.get_bons().len());
+        assert!(import_draft(&settings, &database, &draft, &file, true).is_ok());
+        assert_eq!(2, database.get_bons().len());
+
+        fs::remove_dir_all(&root).expect("Couldn't remove test directory");
+    }
+
+    #[test]
+    fn missing_files() {
+        let cli = Cli::parse_from(["bon-scanner", "--config", "noconfig.toml", "list"]);
+        assert!(cli.settings().is_err());
+
+        let cli = Cli::parse_from([
+            "bon-scanner",
+            "--config",
+            "config/bon-scanner.toml",
+            "--database",
+            "nodatabase.sqlite",
+            "restore",
+            "backup.json",
+        ]);
+        let settings = cli.settings().expect("Couldn't read settings");
+        let command = cli.command.expect("Command is missing");
+        assert!(run(command, &settings).is_err());
+        assert!(!settings.database_exists());
+    }
+}
@@ -1,3 +1,11 @@
+use crate::backup::{
+    BACKUP_VERSION, Backup, BonRecord, BudgetRecord, CategoryRecord, EntryRecord, ProcessedRecord,
+    ProductRecord, RuleRecord, TransactionRecord,
+};
+use regex::Regex;
+use sqlite::Value;
+use std::collections::HashMap;
+
 pub struct Database {
     connection: sqlite::Connection,
 }
@@ -17,16 +25,74 @@ impl Database {
             .expect("Couldn't insert processed");
     }
 
+    pub fn add_processed_file(&self, processed_entry: &str, path: &str, hash: &str, bon_id: i64) {
+        self.insert(
+            "INSERT INTO processed (processedEntry, path, hash, bonId) VALUES (?, ?, ?, ?)",
+            &[
+                Value::from(processed_entry),
+                Value::from(path),
+                Value::from(hash),
+                Value::from(bon_id),
+            ],
+        )
+        .expect("Couldn't insert processed");
+    }
+
+    pub fn apply_rules(&self) -> usize {
+        let rules = self.get_rules();
+        let categories = self.get_categories();
+        let mut updated = 0;
+        self.get_products()
+            .iter()
+            .filter(|product| {
+                !categories.iter().any(|category| {
+                    category.category_id == product.category_id && !category.category.is_empty()
+                })
+            })
+            .for_each(|product| {
+                if let Some(category_id) = Rule::find_category(&rules, &product.product) {
+                    let product_id = product.product_id;
+                    let query = format!(
+                        "UPDATE products SET categoryId = {category_id} WHERE productId = {product_id}"
+                    );
+                    self.connection
+                        .execute(query)
+                        .expect("Couldn't update product");
+                    updated += 1;
+                }
+            });
+        updated
+    }
+
+    fn build_category_tree(
+        tree: &mut Vec<Category>,
+        remaining: &mut Vec<Category>,
+        parent_id: Option<i64>,
+        depth: usize,
+    ) {
+        while let Some(i) = remaining
+            .iter()
+            .position(|category| category.parent_id == parent_id)
+        {
+            let mut category = remaining.remove(i);
+            let category_id = category.category_id;
+            category.depth = depth;
+            tree.push(category);
+            Self::build_category_tree(tree, remaining, Some(category_id), depth + 1);
+        }
+    }
+
     pub fn create_bon(&self, date: &str, price: f64) {
         let query = format!("INSERT INTO bons (date, price) VALUES ('{date}', '{price}')");
         self.connection.execute(query).expect("Couldn't insert bon");
     }
 
     pub fn create_category(&self, category: &str) {
-        let query = format!("INSERT INTO categories (category) VALUES ('{category}')");
-        self.connection
-            .execute(query)
-            .expect("Couldn't insert category");
+        self.insert(
+            "INSERT INTO categories (category) VALUES (?)",
+            &[Value::from(category)],
+        )
+        .expect("Couldn't insert category");
     }
 
     pub fn create_database(&self) {
@@ -41,6 +107,7 @@ impl Database {
         self.connection
             .execute(query)
             .expect("Couldn't create database");
+        self.update_database();
     }
 
     pub fn create_entry(&self, bon_id: i64, product_id: i64, price: f64) {
@@ -53,12 +120,220 @@ impl Database {
     }
 
     pub fn create_product(&self, category_id: i64, product: &str) {
+        self.insert(
+            "INSERT INTO products (categoryId, product) VALUES (?, ?)",
+            &[Value::from(category_id), Value::from(product)],
+        )
+        .expect("Couldn't insert product");
+    }
+
+    // fails for a regex that doesn't compile, it would never match anything
+    pub fn create_rule(
+        &self,
+        pattern: &str,
+        is_regex: bool,
+        category_id: i64,
+        priority: i64,
+    ) -> sqlite::Result<()> {
+        Rule::check_pattern(pattern, is_regex)?;
+        self.insert(
+            "INSERT INTO rules (pattern, isRegex, categoryId, priority) VALUES (?, ?, ?, ?)",
+            &[
+                Value::from(pattern),
+                Value::from(i64::from(is_regex)),
+                Value::from(category_id),
+                Value::from(priority),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn create_transaction(&self, date: &str, amount: f64, description: &str) {
+        self.insert(
+            "INSERT INTO transactions (date, amount, description) VALUES (?, ?, ?)",
+            &[
+                Value::from(date),
+                Value::from(amount),
+                Value::from(description),
+            ],
+        )
+        .expect("Couldn't insert transaction");
+    }
+
+    pub fn delete_budget(&self, category_id: i64) {
+        let query = format!("DELETE FROM budgets WHERE categoryId = {category_id}");
+        self.connection
+            .execute(query)
+            .expect("Couldn't delete budget");
+    }
+
+    // rules and the budget move along with the products, the budget of the target wins; without
+    // a different target they're deleted
+    pub fn delete_category(&self, category_id: i64, reassign_id: i64) {
+        self.move_products(category_id, reassign_id);
         let query = format!(
-            "INSERT INTO products (categoryId, product) VALUES ('{category_id}', '{product}')"
+            "UPDATE rules SET categoryId = {reassign_id} WHERE categoryId = {category_id};
+             UPDATE OR IGNORE budgets SET categoryId = {reassign_id} WHERE categoryId = {category_id}"
         );
         self.connection
             .execute(query)
-            .expect("Couldn't insert product");
+            .expect("Couldn't move category");
+        self.delete_empty_category(category_id);
+    }
+
+    // the category must not have products anymore, its rules and budget are dropped with it
+    pub fn delete_empty_category(&self, category_id: i64) {
+        let query = format!(
+            "UPDATE categories SET parentId = (SELECT parentId FROM categories WHERE categoryId = {category_id})
+             WHERE parentId = {category_id};
+             DELETE FROM rules WHERE categoryId = {category_id};
+             DELETE FROM budgets WHERE categoryId = {category_id};
+             DELETE FROM categories WHERE categoryId = {category_id}"
+        );
+        self.connection
+            .execute(query)
+            .expect("Couldn't delete category");
+    }
+
+    pub fn delete_rule(&self, rule_id: i64) {
+        let query = format!("DELETE FROM rules WHERE ruleId = {rule_id}");
+        self.connection
+            .execute(query)
+            .expect("Couldn't delete rule");
+    }
+
+    // names typed by the user or read from a bon can contain quotes, so bind them instead of
+    // formatting them into the query
+    fn execute(&self, query: &str, values: &[Value]) -> sqlite::Result<()> {
+        let mut statement = self.connection.prepare(query)?;
+        statement.bind(values)?;
+        while let sqlite::State::Row = statement.next()? {}
+        Ok(())
+    }
+
+    pub fn get_backup(&self) -> Backup {
+        let mut backup = Backup {
+            version: BACKUP_VERSION,
+            blacklist: self.get_blacklist(),
+            processed: self
+                .get_processed_files()
+                .into_iter()
+                .map(|processed| ProcessedRecord {
+                    entry: processed.processed_entry,
+                    path: processed.path,
+                    hash: processed.hash,
+                    bon_id: processed.bon_id,
+                    archive_path: processed.archive_path,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let query = "SELECT * FROM bons ORDER BY bonId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            backup.bons.push(BonRecord {
+                bon_id: row.read::<i64, _>("bonId"),
+                date: row.read::<&str, _>("date").to_string(),
+                price: row.read::<f64, _>("price"),
+                store: row.read::<&str, _>("store").to_string(),
+                hidden: row.read::<Option<i64>, _>("hidden").unwrap_or(0) != 0,
+                hide_date: row
+                    .read::<Option<&str>, _>("hide_date")
+                    .map(|hide_date| hide_date.to_string()),
+            });
+        }
+        let query = "SELECT * FROM entries ORDER BY entryId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            backup.entries.push(EntryRecord {
+                entry_id: row.read::<i64, _>("entryId"),
+                bon_id: row.read::<i64, _>("bonId"),
+                product_id: row.read::<i64, _>("productId"),
+                price: row.read::<f64, _>("price"),
+            });
+        }
+        let query = "SELECT * FROM products ORDER BY productId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            backup.products.push(ProductRecord {
+                product_id: row.read::<i64, _>("productId"),
+                category_id: row.read::<i64, _>("categoryId"),
+                product: row.read::<&str, _>("product").to_string(),
+            });
+        }
+        let query = "SELECT * FROM categories ORDER BY categoryId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            backup.categories.push(CategoryRecord {
+                category_id: row.read::<i64, _>("categoryId"),
+                category: row.read::<&str, _>("category").to_string(),
+                color: row
+                    .read::<Option<&str>, _>("color")
+                    .map(|color| color.to_string()),
+                parent_id: row.read::<Option<i64>, _>("parentId"),
+            });
+        }
+        let query = "SELECT * FROM rules ORDER BY ruleId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            backup.rules.push(RuleRecord {
+                rule_id: row.read::<i64, _>("ruleId"),
+                pattern: row.read::<&str, _>("pattern").to_string(),
+                is_regex: row.read::<i64, _>("isRegex") != 0,
+                category_id: row.read::<i64, _>("categoryId"),
+                priority: row.read::<i64, _>("priority"),
+            });
+        }
+        let query = "SELECT * FROM budgets ORDER BY categoryId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            backup.budgets.push(BudgetRecord {
+                category_id: row.read::<i64, _>("categoryId"),
+                monthly_limit: row.read::<f64, _>("monthlyLimit"),
+            });
+        }
+        backup.transactions = self
+            .get_transactions()
+            .into_iter()
+            .map(|transaction| TransactionRecord {
+                transaction_id: transaction.transaction_id,
+                date: transaction.date,
+                amount: transaction.amount,
+                description: transaction.description,
+                bon_id: transaction.bon_id,
+            })
+            .collect();
+        backup
     }
 
     pub fn get_blacklist(&self) -> Vec<String> {
@@ -77,6 +352,27 @@ impl Database {
         blacklist
     }
 
+    // moved or renamed images are found through their archive path
+    pub fn get_bon_image(&self, bon_id: i64) -> Option<String> {
+        let query = format!(
+            "SELECT path, archivePath FROM processed WHERE bonId = {bon_id} ORDER BY processedId DESC LIMIT 1"
+        );
+        let mut statement = self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement");
+        if let Ok(sqlite::State::Row) = statement.next() {
+            let path = statement.read::<String, _>("path").unwrap_or_default();
+            statement
+                .read::<Option<String>, _>("archivePath")
+                .ok()
+                .flatten()
+                .or(Some(path).filter(|path| !path.is_empty()))
+        } else {
+            None
+        }
+    }
+
     pub fn get_bons(&self) -> Vec<Bon> {
         let mut empty_bons: Vec<Bon> = Vec::new();
         let query = "SELECT * FROM bons WHERE hidden = 0";
@@ -90,8 +386,10 @@ impl Database {
             let bon_id = row.read::<i64, _>("bonId");
             let bon_date = row.read::<&str, _>("date");
             let bon_price = row.read::<f64, _>("price");
+            let bon_store = row.read::<&str, _>("store");
             let mut bon = Bon::new(bon_date, bon_price);
             bon.bon_id = bon_id;
+            bon.store = bon_store.to_string();
             empty_bons.push(bon);
         }
         let mut bons: Vec<Bon> = Vec::new();
@@ -99,8 +397,9 @@ impl Database {
             let mut bon = Bon::new(&empty_bon.date, empty_bon.price);
             let bon_id = empty_bon.bon_id;
             bon.bon_id = bon_id;
+            bon.store = empty_bon.store;
             let query = format!(
-                "SELECT category, price, product FROM entries e
+                "SELECT category, color, price, product FROM entries e
                  JOIN products USING (productId)
                  JOIN categories USING (categoryId)
                  WHERE bonId = '{bon_id}'"
@@ -115,7 +414,9 @@ impl Database {
                 let entry_category = row.read::<&str, _>("category");
                 let entry_price = row.read::<f64, _>("price");
                 let entry_product = row.read::<&str, _>("product");
-                let entry = Entry::new(entry_category, entry_product, entry_price);
+                let entry_color = row.read::<Option<&str>, _>("color");
+                let mut entry = Entry::new(entry_category, entry_product, entry_price);
+                entry.color = entry_color.map(|color| color.to_string());
                 bon.entries.push(entry);
             }
             bons.push(bon);
@@ -123,9 +424,64 @@ impl Database {
         bons
     }
 
+    pub fn get_budgets(&self, month: &str) -> Vec<Budget> {
+        let mut limits: Vec<(i64, f64)> = Vec::new();
+        let query = "SELECT categoryId, monthlyLimit FROM budgets";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            let category_id = row.read::<i64, _>("categoryId");
+            let limit = row.read::<f64, _>("monthlyLimit");
+            limits.push((category_id, limit));
+        }
+        let categories = self.get_categories();
+        let spending = self
+            .get_report(ReportPeriod::Month)
+            .into_iter()
+            .find(|report| report.period == month)
+            .map(|report| report.categories)
+            .unwrap_or_default();
+        categories
+            .iter()
+            .map(|category| {
+                let spent = spending
+                    .iter()
+                    .filter(|(name, _)| {
+                        categories.iter().any(|elem| {
+                            &elem.category == name
+                                && (elem.category_id == category.category_id
+                                    || Category::is_descendant_of(
+                                        &categories,
+                                        elem.category_id,
+                                        category.category_id,
+                                    ))
+                        })
+                    })
+                    .map(|(_, total)| total)
+                    .sum();
+                let limit = limits
+                    .iter()
+                    .find(|(category_id, _)| *category_id == category.category_id)
+                    .map(|(_, limit)| *limit);
+                Budget {
+                    category_id: category.category_id,
+                    category: category.category.clone(),
+                    depth: category.depth,
+                    limit,
+                    spent,
+                }
+            })
+            .collect()
+    }
+
     pub fn get_categories(&self) -> Vec<Category> {
         let mut categories: Vec<Category> = Vec::new();
-        let query = "SELECT categoryId, category FROM categories ORDER BY category";
+        let query =
+            "SELECT categoryId, category, color, parentId FROM categories ORDER BY category";
         for row in self
             .connection
             .prepare(query)
@@ -135,10 +491,66 @@ impl Database {
         {
             let category_id = row.read::<i64, _>("categoryId");
             let category_name = row.read::<&str, _>("category");
-            let category = Category::new(category_id, category_name);
+            let category_color = row.read::<Option<&str>, _>("color");
+            let category_parent = row.read::<Option<i64>, _>("parentId");
+            let mut category = Category::new(category_id, category_name);
+            category.color = category_color.map(|color| color.to_string());
+            category.parent_id = category_parent;
             categories.push(category);
         }
-        categories
+        let category_ids: Vec<i64> = categories.iter().map(|elem| elem.category_id).collect();
+        categories.iter_mut().for_each(|category| {
+            if category
+                .parent_id
+                .is_some_and(|parent_id| !category_ids.contains(&parent_id))
+            {
+                category.parent_id = None;
+            }
+        });
+        let mut tree: Vec<Category> = Vec::new();
+        Self::build_category_tree(&mut tree, &mut categories, None, 0);
+        // categories caught in a parent cycle are shown as top level categories
+        tree.append(&mut categories);
+        tree
+    }
+
+    pub fn get_current_date(&self) -> String {
+        let query = "SELECT strftime('%d.%m.%Y', 'now')";
+        let mut statement = self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement");
+        if let Ok(sqlite::State::Row) = statement.next() {
+            statement.read::<String, _>(0).unwrap_or_default()
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn get_current_month(&self) -> String {
+        let query = "SELECT strftime('%Y-%m', 'now')";
+        let mut statement = self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement");
+        if let Ok(sqlite::State::Row) = statement.next() {
+            statement.read::<String, _>(0).unwrap_or_default()
+        } else {
+            String::new()
+        }
+    }
+
+    fn get_database_version(&self) -> i64 {
+        let query = "PRAGMA user_version";
+        let mut statement = self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement");
+        if let Ok(sqlite::State::Row) = statement.next() {
+            statement.read::<i64, _>(0).unwrap_or(0)
+        } else {
+            0
+        }
     }
 
     pub fn get_last_bon_id(&self) -> i64 {
@@ -180,6 +592,70 @@ impl Database {
         }
     }
 
+    pub fn get_price_alerts(&self, threshold: f64) -> Vec<i64> {
+        let mut alerts: Vec<i64> = Vec::new();
+        let query = "SELECT productId, e.price AS price FROM entries e
+                     JOIN bons USING (bonId)
+                     WHERE hidden = 0
+                     ORDER BY productId, date, entryId";
+        let mut last: Option<(i64, f64)> = None;
+        let mut previous: Option<(i64, f64)> = None;
+        let mut check = |last: Option<(i64, f64)>, previous: Option<(i64, f64)>| {
+            if let Some((product_id, price)) = last
+                && let Some((previous_id, previous_price)) = previous
+                && product_id == previous_id
+                && previous_price > 0.0
+                && (price - previous_price) / previous_price * 100.0 > threshold
+            {
+                alerts.push(product_id);
+            }
+        };
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            let product_id = row.read::<i64, _>("productId");
+            let price = row.read::<f64, _>("price");
+            if last.is_some_and(|(last_id, _)| last_id != product_id) {
+                check(last, previous);
+                previous = None;
+            } else {
+                previous = last;
+            }
+            last = Some((product_id, price));
+        }
+        check(last, previous);
+        alerts
+    }
+
+    pub fn get_price_history(&self, product_id: i64) -> Vec<PricePoint> {
+        let mut history: Vec<PricePoint> = Vec::new();
+        let query = format!(
+            "SELECT date, store, e.price AS price FROM entries e
+             JOIN bons USING (bonId)
+             WHERE productId = {product_id} AND hidden = 0
+             ORDER BY date, entryId"
+        );
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            let date = row.read::<&str, _>("date");
+            let store = row.read::<&str, _>("store");
+            let price = row.read::<f64, _>("price");
+            let mut point = PricePoint::new(date, price);
+            point.store = store.to_string();
+            history.push(point);
+        }
+        history
+    }
+
     pub fn get_processed(&self) -> Vec<String> {
         let mut processed: Vec<String> = Vec::new();
         let query = "SELECT processedEntry FROM processed";
@@ -196,6 +672,26 @@ impl Database {
         processed
     }
 
+    pub fn get_processed_files(&self) -> Vec<Processed> {
+        let query = "SELECT * FROM processed ORDER BY processedId";
+        self.connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+            .map(|row| Processed {
+                processed_id: row.read::<i64, _>("processedId"),
+                processed_entry: row.read::<&str, _>("processedEntry").to_string(),
+                path: row.read::<&str, _>("path").to_string(),
+                hash: row.read::<&str, _>("hash").to_string(),
+                bon_id: row.read::<Option<i64>, _>("bonId"),
+                archive_path: row
+                    .read::<Option<&str>, _>("archivePath")
+                    .map(|archive_path| archive_path.to_string()),
+            })
+            .collect()
+    }
+
     pub fn get_products(&self) -> Vec<Product> {
         let mut products: Vec<Product> = Vec::new();
         let query = "SELECT productId, categoryId, product FROM products";
@@ -215,6 +711,123 @@ impl Database {
         products
     }
 
+    pub fn get_report(&self, period: ReportPeriod) -> Vec<PeriodReport> {
+        let length = match period {
+            ReportPeriod::Month => 7,
+            ReportPeriod::Year => 4,
+        };
+        let mut reports: Vec<PeriodReport> = Vec::new();
+        let query = format!(
+            "SELECT substr(date, 1, {length}) AS period, category, SUM(e.price) AS total FROM entries e
+             JOIN bons USING (bonId)
+             JOIN products USING (productId)
+             JOIN categories USING (categoryId)
+             WHERE hidden = 0
+             GROUP BY period, category
+             ORDER BY period, category"
+        );
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            let report_period = row.read::<&str, _>("period");
+            let category = row.read::<&str, _>("category");
+            let total = row.read::<f64, _>("total");
+            if reports
+                .last()
+                .is_none_or(|report| report.period != report_period)
+            {
+                reports.push(PeriodReport::new(report_period));
+            }
+            if let Some(report) = reports.last_mut() {
+                report.categories.push((category.to_string(), total));
+                report.total += total;
+            }
+        }
+        let totals: Vec<(String, f64)> = reports
+            .iter()
+            .map(|report| (report.period.clone(), report.total))
+            .collect();
+        reports.iter_mut().for_each(|report| {
+            let previous = period.previous(&report.period);
+            report.change = totals
+                .iter()
+                .find(|(period, _)| Some(period) == previous.as_ref())
+                .filter(|(_, total)| *total != 0.0)
+                .map(|(_, total)| (report.total - total) / total * 100.0);
+        });
+        reports
+    }
+
+    pub fn get_rules(&self) -> Vec<Rule> {
+        let mut rules: Vec<Rule> = Vec::new();
+        let query = "SELECT ruleId, pattern, isRegex, categoryId, category, priority FROM rules
+                     LEFT JOIN categories USING (categoryId)
+                     ORDER BY priority DESC, ruleId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            let rule_id = row.read::<i64, _>("ruleId");
+            let pattern = row.read::<&str, _>("pattern");
+            let is_regex = row.read::<i64, _>("isRegex") != 0;
+            let category_id = row.read::<i64, _>("categoryId");
+            let category = row.read::<Option<&str>, _>("category").unwrap_or_default();
+            let priority = row.read::<i64, _>("priority");
+            let mut rule = Rule::new(rule_id, pattern, is_regex, category_id, priority);
+            rule.category = category.to_string();
+            rules.push(rule);
+        }
+        rules
+    }
+
+    pub fn get_transactions(&self) -> Vec<Transaction> {
+        let mut transactions: Vec<Transaction> = Vec::new();
+        let query = "SELECT * FROM transactions ORDER BY transactionId";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            transactions.push(Transaction {
+                transaction_id: row.read::<i64, _>("transactionId"),
+                date: row.read::<&str, _>("date").to_string(),
+                amount: row.read::<f64, _>("amount"),
+                description: row.read::<&str, _>("description").to_string(),
+                bon_id: row.read::<Option<i64>, _>("bonId"),
+            });
+        }
+        transactions
+    }
+
+    pub fn get_training_data(&self) -> Vec<(String, String)> {
+        let mut training_data: Vec<(String, String)> = Vec::new();
+        let query = "SELECT product, category FROM entries
+                     JOIN products USING (productId)
+                     JOIN categories USING (categoryId)
+                     WHERE category != ''";
+        for row in self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement")
+            .into_iter()
+            .map(|row| row.expect("Couldn't fetch row"))
+        {
+            let product = row.read::<&str, _>("product");
+            let category = row.read::<&str, _>("category");
+            training_data.push((product.to_string(), category.to_string()));
+        }
+        training_data
+    }
+
     pub fn hide_bon(&self, bon_id: i64) {
         let query = format!(
             "UPDATE bons SET hidden = 1, hide_date = datetime('now') WHERE bonId = {bon_id}"
@@ -222,18 +835,543 @@ impl Database {
         self.connection.execute(query).expect("Couldn't hide bon");
     }
 
+    // a failed backup import must not leave half of the rows behind
+    fn in_transaction(&self, f: impl FnOnce() -> sqlite::Result<()>) -> sqlite::Result<()> {
+        self.connection.execute("BEGIN")?;
+        let result = f().and_then(|()| self.connection.execute("COMMIT"));
+        if result.is_err() {
+            // the error that made the import fail is more useful than a failed rollback
+            self.connection.execute("ROLLBACK").ok();
+        }
+        result
+    }
+
+    fn insert(&self, query: &str, values: &[Value]) -> sqlite::Result<i64> {
+        self.execute(query, values)?;
+        let mut statement = self.connection.prepare("SELECT last_insert_rowid()")?;
+        statement.next()?;
+        statement.read::<i64, _>(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let query = "SELECT (SELECT COUNT(*) FROM bons) + (SELECT COUNT(*) FROM categories) + (SELECT COUNT(*) FROM products)";
+        let mut statement = self
+            .connection
+            .prepare(query)
+            .expect("Couldn't prepare statement");
+        if let Ok(sqlite::State::Row) = statement.next() {
+            statement.read::<i64, _>(0).unwrap_or(0) == 0
+        } else {
+            true
+        }
+    }
+
+    pub fn merge_backup(&self, backup: &Backup) -> sqlite::Result<()> {
+        self.in_transaction(|| self.merge_backup_records(backup))
+    }
+
+    fn merge_backup_records(&self, backup: &Backup) -> sqlite::Result<()> {
+        let existing = self.get_backup();
+        let mut category_ids: HashMap<i64, i64> = HashMap::new();
+        let mut new_categories: Vec<&CategoryRecord> = Vec::new();
+        for category in backup.categories.iter() {
+            let category_id = match existing
+                .categories
+                .iter()
+                .find(|elem| elem.category == category.category)
+            {
+                Some(elem) => elem.category_id,
+                None => {
+                    new_categories.push(category);
+                    self.insert(
+                        "INSERT INTO categories (category, color) VALUES (?, ?)",
+                        &[
+                            Value::from(category.category.as_str()),
+                            Value::from(category.color.as_deref()),
+                        ],
+                    )?
+                }
+            };
+            category_ids.insert(category.category_id, category_id);
+        }
+        for category in new_categories {
+            if let Some(parent_id) = category
+                .parent_id
+                .and_then(|parent_id| category_ids.get(&parent_id))
+            {
+                self.set_category_parent(category_ids[&category.category_id], Some(*parent_id));
+            }
+        }
+        let mut product_ids: HashMap<i64, i64> = HashMap::new();
+        for product in backup.products.iter() {
+            let product_id = match existing
+                .products
+                .iter()
+                .find(|elem| elem.product == product.product)
+            {
+                Some(elem) => elem.product_id,
+                None => self.insert(
+                    "INSERT INTO products (categoryId, product) VALUES (?, ?)",
+                    &[
+                        Value::from(mapped_id(&category_ids, product.category_id, "category")?),
+                        Value::from(product.product.as_str()),
+                    ],
+                )?,
+            };
+            product_ids.insert(product.product_id, product_id);
+        }
+        let mut bon_ids: HashMap<i64, i64> = HashMap::new();
+        for bon in backup.bons.iter() {
+            let entries = sorted_entries(
+                backup
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.bon_id == bon.bon_id)
+                    .map(|entry| {
+                        Ok((
+                            mapped_id(&product_ids, entry.product_id, "product")?,
+                            entry.price,
+                        ))
+                    })
+                    .collect::<sqlite::Result<_>>()?,
+            );
+            // two shopping trips can cost the same, so a bon is only known when its entries match too
+            if let Some(elem) = existing.bons.iter().find(|elem| {
+                elem.date == bon.date
+                    && elem.price == bon.price
+                    && elem.store == bon.store
+                    && sorted_entries(
+                        existing
+                            .entries
+                            .iter()
+                            .filter(|entry| entry.bon_id == elem.bon_id)
+                            .map(|entry| (entry.product_id, entry.price))
+                            .collect(),
+                    ) == entries
+            }) {
+                bon_ids.insert(bon.bon_id, elem.bon_id);
+                continue;
+            }
+            let bon_id = self.insert(
+                "INSERT INTO bons (date, price, hidden, hide_date, store) VALUES (?, ?, ?, ?, ?)",
+                &[
+                    Value::from(bon.date.as_str()),
+                    Value::from(bon.price),
+                    Value::from(i64::from(bon.hidden)),
+                    Value::from(bon.hide_date.as_deref()),
+                    Value::from(bon.store.as_str()),
+                ],
+            )?;
+            bon_ids.insert(bon.bon_id, bon_id);
+            for (product_id, price) in entries {
+                self.insert(
+                    "INSERT INTO entries (bonId, productId, price) VALUES (?, ?, ?)",
+                    &[
+                        Value::from(bon_id),
+                        Value::from(product_id),
+                        Value::from(price),
+                    ],
+                )?;
+            }
+        }
+        for entry in backup
+            .blacklist
+            .iter()
+            .filter(|entry| !existing.blacklist.contains(entry))
+        {
+            self.insert(
+                "INSERT INTO blacklist (blacklistEntry) VALUES (?)",
+                &[Value::from(entry.as_str())],
+            )?;
+        }
+        for processed in backup.processed.iter() {
+            let exists = existing.processed.iter().any(|elem| {
+                elem.entry == processed.entry
+                    && elem.path == processed.path
+                    && elem.hash == processed.hash
+            });
+            if !exists {
+                self.insert(
+                    "INSERT INTO processed (processedEntry, path, hash, bonId, archivePath) VALUES (?, ?, ?, ?, ?)",
+                    &[
+                        Value::from(processed.entry.as_str()),
+                        Value::from(processed.path.as_str()),
+                        Value::from(processed.hash.as_str()),
+                        Value::from(
+                            processed
+                                .bon_id
+                                .and_then(|bon_id| bon_ids.get(&bon_id).copied()),
+                        ),
+                        Value::from(processed.archive_path.as_deref()),
+                    ],
+                )?;
+            }
+        }
+        for rule in backup.rules.iter() {
+            let category_id = mapped_id(&category_ids, rule.category_id, "category")?;
+            let exists = existing
+                .rules
+                .iter()
+                .any(|elem| elem.pattern == rule.pattern && elem.category_id == category_id);
+            if !exists {
+                self.insert(
+                    "INSERT INTO rules (pattern, isRegex, categoryId, priority) VALUES (?, ?, ?, ?)",
+                    &[
+                        Value::from(rule.pattern.as_str()),
+                        Value::from(i64::from(rule.is_regex)),
+                        Value::from(category_id),
+                        Value::from(rule.priority),
+                    ],
+                )?;
+            }
+        }
+        for transaction in backup.transactions.iter() {
+            let exists = existing.transactions.iter().any(|elem| {
+                elem.date == transaction.date
+                    && elem.amount == transaction.amount
+                    && elem.description == transaction.description
+            });
+            if !exists {
+                self.insert(
+                    "INSERT INTO transactions (date, amount, description, bonId) VALUES (?, ?, ?, ?)",
+                    &[
+                        Value::from(transaction.date.as_str()),
+                        Value::from(transaction.amount),
+                        Value::from(transaction.description.as_str()),
+                        Value::from(
+                            transaction
+                                .bon_id
+                                .and_then(|bon_id| bon_ids.get(&bon_id).copied()),
+                        ),
+                    ],
+                )?;
+            }
+        }
+        for budget in backup.budgets.iter() {
+            let category_id = mapped_id(&category_ids, budget.category_id, "category")?;
+            self.insert(
+                "INSERT OR IGNORE INTO budgets (categoryId, monthlyLimit) VALUES (?, ?)",
+                &[Value::from(category_id), Value::from(budget.monthly_limit)],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn merge_categories(&self, source_id: i64, target_id: i64) {
+        self.delete_category(source_id, target_id);
+    }
+
+    fn move_products(&self, source_id: i64, target_id: i64) {
+        let query =
+            format!("UPDATE products SET categoryId = {target_id} WHERE categoryId = {source_id}");
+        self.connection
+            .execute(query)
+            .expect("Couldn't move products");
+    }
+
     pub fn new(database_file: &str) -> Self {
         Self {
             connection: sqlite::open(database_file).expect("Couldn't open database"),
         }
     }
+
+    pub fn pair_transaction(&self, transaction_id: i64, bon_id: Option<i64>) {
+        let bon_id = bon_id.map_or("NULL".to_string(), |bon_id| bon_id.to_string());
+        let query = format!(
+            "UPDATE transactions SET bonId = {bon_id} WHERE transactionId = {transaction_id}"
+        );
+        self.connection
+            .execute(query)
+            .expect("Couldn't pair transaction");
+    }
+
+    // fails when another category already has the name
+    pub fn rename_category(&self, category_id: i64, category: &str) -> sqlite::Result<()> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT COUNT(*) FROM categories WHERE category = ? AND categoryId != ?")?;
+        statement.bind(&[Value::from(category), Value::from(category_id)][..])?;
+        if let sqlite::State::Row = statement.next()?
+            && statement.read::<i64, _>(0)? > 0
+        {
+            return Err(sqlite::Error {
+                code: None,
+                message: Some(format!("category {category} already exists")),
+            });
+        }
+        self.execute(
+            "UPDATE categories SET category = ? WHERE categoryId = ?",
+            &[Value::from(category), Value::from(category_id)],
+        )
+    }
+
+    pub fn restore_backup(&self, backup: &Backup) -> sqlite::Result<()> {
+        self.in_transaction(|| self.restore_backup_records(backup))
+    }
+
+    fn restore_backup_records(&self, backup: &Backup) -> sqlite::Result<()> {
+        for bon in backup.bons.iter() {
+            self.insert(
+                "INSERT INTO bons (bonId, date, price, hidden, hide_date, store) VALUES (?, ?, ?, ?, ?, ?)",
+                &[
+                    Value::from(bon.bon_id),
+                    Value::from(bon.date.as_str()),
+                    Value::from(bon.price),
+                    Value::from(i64::from(bon.hidden)),
+                    Value::from(bon.hide_date.as_deref()),
+                    Value::from(bon.store.as_str()),
+                ],
+            )?;
+        }
+        for entry in backup.entries.iter() {
+            self.insert(
+                "INSERT INTO entries (entryId, bonId, productId, price) VALUES (?, ?, ?, ?)",
+                &[
+                    Value::from(entry.entry_id),
+                    Value::from(entry.bon_id),
+                    Value::from(entry.product_id),
+                    Value::from(entry.price),
+                ],
+            )?;
+        }
+        for product in backup.products.iter() {
+            self.insert(
+                "INSERT INTO products (productId, categoryId, product) VALUES (?, ?, ?)",
+                &[
+                    Value::from(product.product_id),
+                    Value::from(product.category_id),
+                    Value::from(product.product.as_str()),
+                ],
+            )?;
+        }
+        for category in backup.categories.iter() {
+            self.insert(
+                "INSERT INTO categories (categoryId, category, color, parentId) VALUES (?, ?, ?, ?)",
+                &[
+                    Value::from(category.category_id),
+                    Value::from(category.category.as_str()),
+                    Value::from(category.color.as_deref()),
+                    Value::from(category.parent_id),
+                ],
+            )?;
+        }
+        for entry in backup.blacklist.iter() {
+            self.insert(
+                "INSERT INTO blacklist (blacklistEntry) VALUES (?)",
+                &[Value::from(entry.as_str())],
+            )?;
+        }
+        for processed in backup.processed.iter() {
+            self.insert(
+                "INSERT INTO processed (processedEntry, path, hash, bonId, archivePath) VALUES (?, ?, ?, ?, ?)",
+                &[
+                    Value::from(processed.entry.as_str()),
+                    Value::from(processed.path.as_str()),
+                    Value::from(processed.hash.as_str()),
+                    Value::from(processed.bon_id),
+                    Value::from(processed.archive_path.as_deref()),
+                ],
+            )?;
+        }
+        for rule in backup.rules.iter() {
+            self.insert(
+                "INSERT INTO rules (ruleId, pattern, isRegex, categoryId, priority) VALUES (?, ?, ?, ?, ?)",
+                &[
+                    Value::from(rule.rule_id),
+                    Value::from(rule.pattern.as_str()),
+                    Value::from(i64::from(rule.is_regex)),
+                    Value::from(rule.category_id),
+                    Value::from(rule.priority),
+                ],
+            )?;
+        }
+        for transaction in backup.transactions.iter() {
+            self.insert(
+                "INSERT INTO transactions (transactionId, date, amount, description, bonId) VALUES (?, ?, ?, ?, ?)",
+                &[
+                    Value::from(transaction.transaction_id),
+                    Value::from(transaction.date.as_str()),
+                    Value::from(transaction.amount),
+                    Value::from(transaction.description.as_str()),
+                    Value::from(transaction.bon_id),
+                ],
+            )?;
+        }
+        for budget in backup.budgets.iter() {
+            self.insert(
+                "INSERT INTO budgets (categoryId, monthlyLimit) VALUES (?, ?)",
+                &[
+                    Value::from(budget.category_id),
+                    Value::from(budget.monthly_limit),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn set_bon_store(&self, bon_id: i64, store: &str) {
+        self.execute(
+            "UPDATE bons SET store = ? WHERE bonId = ?",
+            &[Value::from(store), Value::from(bon_id)],
+        )
+        .expect("Couldn't set bon store");
+    }
+
+    pub fn set_budget(&self, category_id: i64, limit: f64) {
+        let query = format!(
+            "INSERT OR REPLACE INTO budgets (categoryId, monthlyLimit) VALUES ('{category_id}', '{limit}')"
+        );
+        self.connection.execute(query).expect("Couldn't set budget");
+    }
+
+    pub fn set_category_color(&self, category_id: i64, color: Option<&str>) {
+        let color = color.map_or_else(|| "NULL".to_string(), |color| format!("'{color}'"));
+        let query =
+            format!("UPDATE categories SET color = {color} WHERE categoryId = {category_id}");
+        self.connection
+            .execute(query)
+            .expect("Couldn't set category color");
+    }
+
+    pub fn set_category_parent(&self, category_id: i64, parent_id: Option<i64>) {
+        let parent_id = parent_id.map_or_else(|| "NULL".to_string(), |id| id.to_string());
+        let query = format!(
+            "UPDATE categories SET parentId = {parent_id} WHERE categoryId = {category_id}"
+        );
+        self.connection
+            .execute(query)
+            .expect("Couldn't set category parent");
+    }
+
+    pub fn set_processed_archive(&self, bon_id: i64, archive_path: &str) {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE processed SET archivePath = ? WHERE bonId = ?")
+            .expect("Couldn't prepare statement");
+        statement
+            .bind(&[Value::from(archive_path), Value::from(bon_id)][..])
+            .expect("Couldn't bind values");
+        while let Ok(sqlite::State::Row) = statement.next() {}
+    }
+
+    pub fn set_rule_priority(&self, rule_id: i64, priority: i64) {
+        let query = format!("UPDATE rules SET priority = {priority} WHERE ruleId = {rule_id}");
+        self.connection
+            .execute(query)
+            .expect("Couldn't set rule priority");
+    }
+
+    pub fn set_rule_regex(&self, rule_id: i64, is_regex: bool) -> sqlite::Result<()> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT pattern FROM rules WHERE ruleId = ?")?;
+        statement.bind((1, rule_id))?;
+        if let sqlite::State::Row = statement.next()? {
+            Rule::check_pattern(&statement.read::<String, _>(0)?, is_regex)?;
+        }
+        let is_regex = i64::from(is_regex);
+        let query = format!("UPDATE rules SET isRegex = {is_regex} WHERE ruleId = {rule_id}");
+        self.connection.execute(query)
+    }
+
+    pub fn update_database(&self) {
+        let version = self.get_database_version();
+        if version < 1 {
+            let query = "
+                ALTER TABLE categories ADD COLUMN color TEXT DEFAULT NULL;
+                PRAGMA user_version = 1;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 2 {
+            let query = "
+                ALTER TABLE categories ADD COLUMN parentId INTEGER DEFAULT NULL;
+                PRAGMA user_version = 2;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 3 {
+            let query = "
+                CREATE TABLE rules (ruleId INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, isRegex INTEGER DEFAULT 0, categoryId INTEGER NOT NULL, priority INTEGER DEFAULT 0);
+                PRAGMA user_version = 3;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 4 {
+            let query = "
+                ALTER TABLE bons ADD COLUMN store TEXT NOT NULL DEFAULT '';
+                PRAGMA user_version = 4;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 5 {
+            let query = "
+                CREATE TABLE budgets (budgetId INTEGER PRIMARY KEY AUTOINCREMENT, categoryId INTEGER NOT NULL UNIQUE, monthlyLimit REAL NOT NULL);
+                PRAGMA user_version = 5;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 6 {
+            let query = "
+                CREATE TABLE transactions (transactionId INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT NOT NULL, amount REAL NOT NULL, description TEXT NOT NULL DEFAULT '', bonId INTEGER DEFAULT NULL);
+                PRAGMA user_version = 6;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 7 {
+            let query = "
+                CREATE TABLE IF NOT EXISTS processed (processedId INTEGER PRIMARY KEY AUTOINCREMENT, processedEntry TEXT NOT NULL);
+                ALTER TABLE processed ADD COLUMN path TEXT NOT NULL DEFAULT '';
+                ALTER TABLE processed ADD COLUMN hash TEXT NOT NULL DEFAULT '';
+                ALTER TABLE processed ADD COLUMN bonId INTEGER DEFAULT NULL;
+                PRAGMA user_version = 7;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+        if version < 8 {
+            let query = "
+                ALTER TABLE processed ADD COLUMN archivePath TEXT DEFAULT NULL;
+                PRAGMA user_version = 8;
+            ";
+            self.connection
+                .execute(query)
+                .expect("Couldn't update database");
+        }
+    }
+}
+
+fn mapped_id(ids: &HashMap<i64, i64>, id: i64, table: &str) -> sqlite::Result<i64> {
+    ids.get(&id).copied().ok_or_else(|| sqlite::Error {
+        code: None,
+        message: Some(format!("backup references the missing {table} {id}")),
+    })
+}
+
+fn sorted_entries(mut entries: Vec<(i64, f64)>) -> Vec<(i64, f64)> {
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    entries
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Bon {
     pub bon_id: i64,
     pub date: String,
     pub price: f64,
+    pub store: String,
     pub entries: Vec<Entry>,
 }
 
@@ -243,6 +1381,7 @@ impl Default for Bon {
             bon_id: 0,
             date: String::new(),
             price: 0.0,
+            store: String::new(),
             entries: Vec::new(),
         }
     }
@@ -254,14 +1393,31 @@ impl Bon {
             bon_id: 0,
             date: date.to_string(),
             price,
+            store: String::new(),
             entries: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+// dates can carry a time and zone, e.g. 2024-12-24 12:12:12 +0100, when only the day is needed
+pub fn day(date: &str) -> &str {
+    date.get(..10).unwrap_or(date)
+}
+
+// first month of a window of the given number of months ending with the month of date, e.g.
+// 2024-02 for 12 months up to 2025-01-24
+pub fn month_cutoff(date: &str, months: usize) -> Option<String> {
+    let mut cutoff = date.get(..7)?.to_string();
+    for _ in 1..months {
+        cutoff = ReportPeriod::Month.previous(&cutoff)?;
+    }
+    Some(cutoff)
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Entry {
     pub category: String,
+    pub color: Option<String>,
     pub product: String,
     pub price: f64,
 }
@@ -270,15 +1426,27 @@ impl Entry {
     pub fn new(category: &str, product: &str, price: f64) -> Self {
         Self {
             category: category.to_string(),
+            color: None,
             product: product.to_string(),
             price,
         }
     }
 }
 
+pub struct Budget {
+    pub category_id: i64,
+    pub category: String,
+    pub depth: usize,
+    pub limit: Option<f64>,
+    pub spent: f64,
+}
+
 pub struct Category {
     pub category_id: i64,
     pub category: String,
+    pub color: Option<String>,
+    pub depth: usize,
+    pub parent_id: Option<i64>,
 }
 
 impl Category {
@@ -286,10 +1454,71 @@ impl Category {
         Self {
             category_id,
             category: category.to_string(),
+            color: None,
+            depth: 0,
+            parent_id: None,
+        }
+    }
+
+    pub fn ancestor_at_level<'a>(
+        categories: &'a [Category],
+        category: &str,
+        level: usize,
+    ) -> Option<&'a Category> {
+        let path = Category::path(categories, category);
+        path.get(level).or(path.last()).copied()
+    }
+
+    pub fn is_descendant_of(categories: &[Category], category_id: i64, ancestor_id: i64) -> bool {
+        let mut current = categories
+            .iter()
+            .find(|elem| elem.category_id == category_id)
+            .and_then(|elem| elem.parent_id);
+        let mut steps = 0;
+        while let Some(parent_id) = current
+            && steps < categories.len()
+        {
+            if parent_id == ancestor_id {
+                return true;
+            }
+            current = categories
+                .iter()
+                .find(|elem| elem.category_id == parent_id)
+                .and_then(|elem| elem.parent_id);
+            steps += 1;
+        }
+        false
+    }
+
+    pub fn path<'a>(categories: &'a [Category], category: &str) -> Vec<&'a Category> {
+        let mut path: Vec<&Category> = Vec::new();
+        let mut current = categories.iter().find(|elem| elem.category == category);
+        while let Some(elem) = current {
+            if path
+                .iter()
+                .any(|known| known.category_id == elem.category_id)
+            {
+                break;
+            }
+            path.push(elem);
+            current = elem
+                .parent_id
+                .and_then(|parent_id| categories.iter().find(|elem| elem.category_id == parent_id));
         }
+        path.reverse();
+        path
     }
 }
 
+pub struct Processed {
+    pub processed_id: i64,
+    pub processed_entry: String,
+    pub path: String,
+    pub hash: String,
+    pub bon_id: Option<i64>,
+    pub archive_path: Option<String>,
+}
+
 pub struct Product {
     pub product_id: i64,
     pub category_id: i64,
@@ -306,11 +1535,188 @@ impl Product {
     }
 }
 
+pub struct PeriodReport {
+    pub period: String,
+    pub categories: Vec<(String, f64)>,
+    pub total: f64,
+    pub change: Option<f64>,
+}
+
+impl PeriodReport {
+    pub fn new(period: &str) -> Self {
+        Self {
+            period: period.to_string(),
+            categories: Vec::new(),
+            total: 0.0,
+            change: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ReportPeriod {
+    Month,
+    Year,
+}
+
+impl ReportPeriod {
+    pub fn previous(&self, period: &str) -> Option<String> {
+        match self {
+            ReportPeriod::Month => {
+                let (year, month) = period.split_once('-')?;
+                let year = year.parse::<i64>().ok()?;
+                let month = month.parse::<i64>().ok()?;
+                if month > 1 {
+                    Some(format!("{year:04}-{:02}", month - 1))
+                } else {
+                    Some(format!("{:04}-12", year - 1))
+                }
+            }
+            ReportPeriod::Year => {
+                let year = period.parse::<i64>().ok()?;
+                Some(format!("{:04}", year - 1))
+            }
+        }
+    }
+}
+
+pub struct PricePoint {
+    pub date: String,
+    pub price: f64,
+    pub store: String,
+}
+
+impl PricePoint {
+    pub fn new(date: &str, price: f64) -> Self {
+        Self {
+            date: date.to_string(),
+            price,
+            store: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PriceStats {
+    pub average: f64,
+    pub change: f64,
+    pub last: f64,
+    pub max: f64,
+    pub min: f64,
+}
+
+impl PriceStats {
+    pub fn new(history: &[PricePoint]) -> Option<Self> {
+        let first = history.first()?;
+        let last = history.last()?;
+        let prices = history.iter().map(|point| point.price);
+        let change = if first.price != 0.0 {
+            (last.price - first.price) / first.price * 100.0
+        } else {
+            0.0
+        };
+        Some(Self {
+            average: prices.clone().sum::<f64>() / history.len() as f64,
+            change,
+            last: last.price,
+            max: prices.clone().fold(f64::MIN, f64::max),
+            min: prices.fold(f64::MAX, f64::min),
+        })
+    }
+
+    pub fn window(history: &[PricePoint], months: usize) -> &[PricePoint] {
+        let Some(last) = history.last() else {
+            return history;
+        };
+        let Some(cutoff) = month_cutoff(&last.date, months) else {
+            return history;
+        };
+        let start = history
+            .iter()
+            .position(|point| point.date >= cutoff)
+            .unwrap_or(0);
+        &history[start..]
+    }
+}
+
+pub struct Rule {
+    pub rule_id: i64,
+    pub pattern: String,
+    pub is_regex: bool,
+    pub category_id: i64,
+    pub category: String,
+    pub priority: i64,
+    regex: Option<Regex>,
+}
+
+impl Rule {
+    pub fn new(
+        rule_id: i64,
+        pattern: &str,
+        is_regex: bool,
+        category_id: i64,
+        priority: i64,
+    ) -> Self {
+        // compiled once, the rules are matched against every product
+        let regex = if is_regex {
+            Regex::new(pattern).ok()
+        } else {
+            None
+        };
+        Self {
+            rule_id,
+            pattern: pattern.to_string(),
+            is_regex,
+            category_id,
+            category: String::new(),
+            priority,
+            regex,
+        }
+    }
+
+    pub fn check_pattern(pattern: &str, is_regex: bool) -> sqlite::Result<()> {
+        if is_regex && let Err(error) = Regex::new(pattern) {
+            return Err(sqlite::Error {
+                code: None,
+                message: Some(format!("invalid regex {pattern}: {error}")),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn find_category(rules: &[Rule], product: &str) -> Option<i64> {
+        rules
+            .iter()
+            .find(|rule| rule.matches(product))
+            .map(|rule| rule.category_id)
+    }
+
+    pub fn matches(&self, product: &str) -> bool {
+        if self.is_regex {
+            self.regex.as_ref().is_some_and(|re| re.is_match(product))
+        } else {
+            product
+                .to_lowercase()
+                .contains(&self.pattern.to_lowercase())
+        }
+    }
+}
+
+pub struct Transaction {
+    pub transaction_id: i64,
+    pub date: String,
+    pub amount: f64,
+    pub description: String,
+    pub bon_id: Option<i64>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use sqlite::State;
 
+    const DATABASE_VERSION: i64 = 8;
+
     #[test]
     fn create_database() {
         let mut tables: Vec<String> = Vec::new();
@@ -326,14 +1732,39 @@ mod tests {
                 true
             })
             .expect("Couldn't execute query");
-        assert_eq!(7, tables.len());
+        assert_eq!(10, tables.len());
         assert!(tables.contains(&"blacklist".to_string()));
         assert!(tables.contains(&"bons".to_string()));
+        assert!(tables.contains(&"budgets".to_string()));
         assert!(tables.contains(&"categories".to_string()));
         assert!(tables.contains(&"entries".to_string()));
         assert!(tables.contains(&"processed".to_string()));
         assert!(tables.contains(&"products".to_string()));
+        assert!(tables.contains(&"rules".to_string()));
         assert!(tables.contains(&"sqlite_sequence".to_string()));
+        assert!(tables.contains(&"transactions".to_string()));
+        assert_eq!(DATABASE_VERSION, database.get_database_version());
+    }
+
+    #[test]
+    fn update_database() {
+        let database = Database::new(":memory:");
+        database
+            .connection
+            .execute(
+                "
+                CREATE TABLE bons (bonId INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT NOT NULL, price REAL NOT NULL, hidden INTEGER DEFAULT 0, hide_date DEFAULT NULL);
+                CREATE TABLE categories (categoryId INTEGER PRIMARY KEY AUTOINCREMENT, category TEXT NOT NULL);
+                CREATE TABLE products (productId INTEGER PRIMARY KEY AUTOINCREMENT, categoryId INTEGER NOT NULL, product TEXT NOT NULL);
+                ",
+            )
+            .expect("Couldn't create tables");
+        assert_eq!(0, database.get_database_version());
+        database.update_database();
+        assert_eq!(DATABASE_VERSION, database.get_database_version());
+        database.create_category("food");
+        database.set_category_color(1, Some("red"));
+        assert_eq!(Some("red".to_string()), database.get_categories()[0].color);
     }
 
     #[test]
@@ -385,6 +1816,9 @@ mod tests {
             assert_eq!(0, hidden);
         }
 
+        database.set_bon_store(1, "supermarket");
+        assert_eq!("supermarket", database.get_bons()[0].store);
+
         database.hide_bon(1);
         let query = "SELECT hidden, hide_date FROM bons";
 
@@ -405,6 +1839,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn budgets() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-12-01", 0.0);
+        database.create_bon("2024-12-24", 0.0);
+        database.create_bon("2025-01-24", 0.0);
+        database.create_category("food");
+        database.create_category("dairy");
+        database.create_category("stuff");
+        database.set_category_parent(2, Some(1));
+        database.create_product(1, "bread");
+        database.create_product(2, "butter");
+        database.create_product(3, "spoon");
+        database.create_entry(1, 1, 3.0);
+        database.create_entry(2, 2, 2.0);
+        database.create_entry(2, 3, 5.0);
+        database.create_entry(3, 1, 4.0);
+
+        database.set_budget(1, 10.0);
+        database.set_budget(2, 1.5);
+        database.set_budget(2, 2.5);
+
+        let budgets = database.get_budgets("2024-12");
+        assert_eq!(3, budgets.len());
+        let food = &budgets[0];
+        assert_eq!("food", food.category);
+        assert_eq!(Some(10.0), food.limit);
+        assert_eq!(5.0, food.spent);
+        let dairy = &budgets[1];
+        assert_eq!(1, dairy.depth);
+        assert_eq!(Some(2.5), dairy.limit);
+        assert_eq!(2.0, dairy.spent);
+        let stuff = &budgets[2];
+        assert_eq!(None, stuff.limit);
+        assert_eq!(5.0, stuff.spent);
+
+        database.delete_budget(2);
+        let budgets = database.get_budgets("2025-01");
+        assert_eq!(4.0, budgets[0].spent);
+        assert_eq!(None, budgets[1].limit);
+        assert_eq!(0.0, budgets[1].spent);
+
+        assert_eq!(7, database.get_current_month().len());
+        assert_eq!(10, database.get_current_date().len());
+    }
+
     #[test]
     fn categories() {
         let database = Database::new(":memory:");
@@ -421,6 +1902,206 @@ mod tests {
         let category = &categories[0];
         assert_eq!(category.category_id, 1);
         assert_eq!(category.category, "food");
+        assert_eq!(category.color, None);
+
+        assert!(database.rename_category(1, "groceries").is_ok());
+        database.set_category_color(1, Some("green"));
+        let categories = database.get_categories();
+        let category = &categories[0];
+        assert_eq!(category.category, "groceries");
+        assert_eq!(category.color, Some("green".to_string()));
+
+        database.set_category_color(1, None);
+        let categories = database.get_categories();
+        assert_eq!(categories[0].color, None);
+
+        // names are unique, a clash is reported instead of ignored
+        database.create_category("drinks");
+        assert!(database.rename_category(2, "groceries").is_err());
+        assert!(database.rename_category(2, "it's drinks").is_ok());
+        let categories = database.get_categories();
+        assert_eq!("it's drinks", categories[1].category);
+    }
+
+    #[test]
+    fn category_tree() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("household");
+        database.create_category("food");
+        database.create_category("dairy");
+        database.create_category("cleaning");
+        database.create_category("cheese");
+        database.set_category_parent(3, Some(2));
+        database.set_category_parent(4, Some(1));
+        database.set_category_parent(5, Some(3));
+
+        let categories = database.get_categories();
+        let tree: Vec<(&str, usize)> = categories
+            .iter()
+            .map(|category| (category.category.as_str(), category.depth))
+            .collect();
+        assert_eq!(
+            vec![
+                ("food", 0),
+                ("dairy", 1),
+                ("cheese", 2),
+                ("household", 0),
+                ("cleaning", 1)
+            ],
+            tree
+        );
+
+        let ancestor = |category, level| {
+            Category::ancestor_at_level(&categories, category, level)
+                .map(|category| category.category.as_str())
+        };
+        assert_eq!(Some("food"), ancestor("cheese", 0));
+        assert_eq!(Some("dairy"), ancestor("cheese", 1));
+        assert_eq!(Some("cheese"), ancestor("cheese", 2));
+        assert_eq!(Some("cleaning"), ancestor("cleaning", 5));
+        assert_eq!(None, ancestor("unknown", 0));
+
+        assert!(Category::is_descendant_of(&categories, 5, 2));
+        assert!(!Category::is_descendant_of(&categories, 2, 5));
+        assert!(!Category::is_descendant_of(&categories, 4, 2));
+
+        let path: Vec<&str> = Category::path(&categories, "cheese")
+            .iter()
+            .map(|category| category.category.as_str())
+            .collect();
+        assert_eq!(vec!["food", "dairy", "cheese"], path);
+        assert!(Category::path(&categories, "unknown").is_empty());
+
+        database.delete_category(3, 2);
+        let categories = database.get_categories();
+        let cheese = categories
+            .iter()
+            .find(|category| category.category == "cheese")
+            .expect("Couldn't find cheese");
+        assert_eq!(Some(2), cheese.parent_id);
+        assert_eq!(1, cheese.depth);
+
+        database.set_category_parent(5, None);
+        let categories = database.get_categories();
+        assert!(categories.iter().all(|category| category.category_id != 5
+            || (category.parent_id.is_none() && category.depth == 0)));
+    }
+
+    #[test]
+    fn delete_category() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("food");
+        database.create_category("stuff");
+        database.create_product(1, "butter");
+        database.create_product(2, "spoon");
+
+        database.delete_category(2, 1);
+        let categories = database.get_categories();
+        assert_eq!(1, categories.len());
+        assert_eq!(categories[0].category, "food");
+        let products = database.get_products();
+        assert!(products.iter().all(|product| product.category_id == 1));
+    }
+
+    #[test]
+    fn delete_empty_category() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("food");
+        database.create_category("dairy");
+        database.create_category("milk");
+        database.set_category_parent(2, Some(1));
+        database.set_category_parent(3, Some(2));
+        database
+            .create_rule("milk", false, 2, 0)
+            .expect("Couldn\'t create rule");
+        database.set_budget(2, 50.0);
+
+        database.delete_empty_category(2);
+        let categories = database.get_categories();
+        assert_eq!(2, categories.len());
+        assert_eq!(Some(1), categories[1].parent_id);
+        assert!(database.get_rules().is_empty());
+        assert!(
+            database
+                .get_budgets("2025-01")
+                .iter()
+                .all(|budget| budget.limit.is_none())
+        );
+    }
+
+    #[test]
+    fn delete_category_with_rules() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("");
+        database.create_category("dairy");
+        database.create_category("food");
+        database.create_category("drinks");
+        database.create_product(1, "Milk");
+        database.create_bon("2025-01-10", 0.99);
+        database.create_entry(1, 1, 0.99);
+        database
+            .create_rule("milk", false, 2, 0)
+            .expect("Couldn\'t create rule");
+        database
+            .create_rule("juice", false, 4, 0)
+            .expect("Couldn\'t create rule");
+        database.set_budget(2, 50.0);
+        database.set_budget(3, 100.0);
+        database.set_budget(4, 20.0);
+
+        database.delete_category(2, 3);
+        let rules = database.get_rules();
+        assert!(rules.iter().any(|rule| rule.category_id == 3));
+        assert_eq!(1, database.apply_rules());
+        let bons = database.get_bons();
+        assert_eq!(1, bons[0].entries.len());
+        assert_eq!("food", bons[0].entries[0].category);
+        // the target keeps its own budget
+        let budget_count = || {
+            let mut statement = database
+                .connection
+                .prepare("SELECT COUNT(*) FROM budgets")
+                .unwrap();
+            statement.next().unwrap();
+            statement.read::<i64, _>(0).unwrap()
+        };
+        assert_eq!(2, budget_count());
+        let budgets = database.get_budgets("2025-01");
+        let food = budgets
+            .iter()
+            .find(|budget| budget.category_id == 3)
+            .unwrap();
+        assert_eq!(Some(100.0), food.limit);
+
+        // nothing to reassign to, the rule and budget go with the category
+        database.delete_category(4, 4);
+        assert_eq!(1, database.get_rules().len());
+        assert_eq!(1, budget_count());
+    }
+
+    #[test]
+    fn merge_categories() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("food");
+        database.create_category("fod");
+        database.create_category("stuff");
+        database.create_product(1, "butter");
+        database.create_product(2, "eggs");
+        database.create_product(3, "spoon");
+
+        database.merge_categories(2, 1);
+        let categories = database.get_categories();
+        assert_eq!(2, categories.len());
+        assert!(!categories.iter().any(|category| category.category == "fod"));
+        let products = database.get_products();
+        assert_eq!(1, products[0].category_id);
+        assert_eq!(1, products[1].category_id);
+        assert_eq!(3, products[2].category_id);
     }
 
     #[test]
@@ -510,6 +2191,48 @@ mod tests {
         assert!(processed.contains(&"third".to_string()));
     }
 
+    #[test]
+    fn bon_image() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-12-24", 3.0);
+        database.create_bon("2024-12-25", 4.0);
+        database.add_processed_entry("old.jpg");
+        database.add_processed_file("IMG_1.jpg", "/inbox/IMG_1.jpg", "abc", 1);
+        assert_eq!(
+            Some("/inbox/IMG_1.jpg".to_string()),
+            database.get_bon_image(1)
+        );
+        database.set_processed_archive(1, "/archive/IMG_1.jpg");
+        assert_eq!(
+            Some("/archive/IMG_1.jpg".to_string()),
+            database.get_bon_image(1)
+        );
+        assert_eq!(None, database.get_bon_image(2));
+    }
+
+    #[test]
+    fn processed_files() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.add_processed_entry("old.jpg");
+        database.create_bon("2024-12-24", 3.0);
+        database.add_processed_file("IMG_1.jpg", "/inbox/IMG_1.jpg", "abc", 1);
+
+        let processed = database.get_processed_files();
+        assert_eq!(2, processed.len());
+        assert_eq!("old.jpg", processed[0].processed_entry);
+        assert!(processed[0].path.is_empty());
+        assert_eq!(None, processed[0].bon_id);
+        assert_eq!("/inbox/IMG_1.jpg", processed[1].path);
+        assert_eq!("abc", processed[1].hash);
+        assert_eq!(Some(1), processed[1].bon_id);
+        assert_eq!(
+            vec!["old.jpg".to_string(), "IMG_1.jpg".to_string()],
+            database.get_processed()
+        );
+    }
+
     #[test]
     fn products() {
         let database = Database::new(":memory:");
@@ -527,4 +2250,241 @@ mod tests {
         assert_eq!(product.category_id, 1);
         assert_eq!(product.product, "butter");
     }
+
+    #[test]
+    fn rules() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("");
+        database.create_category("dairy");
+        database.create_category("cleaning");
+        database.create_product(1, "Weidemilch 3,5%");
+        database.create_product(1, "Spuelmittel");
+        database.create_product(1, "Toast");
+        database.create_product(3, "Milchschnitte");
+
+        assert!(database.get_rules().is_empty());
+        database
+            .create_rule("milch", false, 2, 0)
+            .expect("Couldn\'t create rule");
+        database
+            .create_rule(r"^Sp\w+mittel$", true, 3, 5)
+            .expect("Couldn\'t create rule");
+        database
+            .create_rule("mittel", false, 2, 1)
+            .expect("Couldn\'t create rule");
+
+        let rules = database.get_rules();
+        assert_eq!(3, rules.len());
+        assert_eq!(5, rules[0].priority);
+        assert!(rules[0].is_regex);
+        assert_eq!("cleaning", rules[0].category);
+        assert_eq!(Some(3), Rule::find_category(&rules, "Spuelmittel"));
+        assert_eq!(Some(2), Rule::find_category(&rules, "Haarmittel"));
+        assert_eq!(Some(2), Rule::find_category(&rules, "MILCH"));
+        assert_eq!(None, Rule::find_category(&rules, "Toast"));
+
+        assert_eq!(2, database.apply_rules());
+        let products = database.get_products();
+        assert_eq!(2, products[0].category_id);
+        assert_eq!(3, products[1].category_id);
+        assert_eq!(1, products[2].category_id);
+        assert_eq!(3, products[3].category_id);
+
+        database.set_rule_priority(1, 10);
+        database
+            .set_rule_regex(1, true)
+            .expect("Couldn't set rule type");
+        let rules = database.get_rules();
+        assert_eq!(1, rules[0].rule_id);
+        assert!(rules[0].is_regex);
+
+        database.delete_rule(1);
+        assert_eq!(2, database.get_rules().len());
+
+        // quotes are stored as typed and broken regexes are refused
+        database
+            .create_rule("children's", false, 2, 0)
+            .expect("Couldn't create rule");
+        assert_eq!("children's", database.get_rules()[2].pattern);
+        assert!(database.create_rule("milk(", true, 2, 0).is_err());
+        assert!(database.set_rule_regex(4, true).is_ok());
+        database
+            .create_rule("(", false, 2, 0)
+            .expect("Couldn't create rule");
+        assert!(database.set_rule_regex(5, true).is_err());
+        assert_eq!(4, database.get_rules().len());
+    }
+
+    #[test]
+    fn transactions() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-12-01", 12.34);
+        database.create_transaction("2024-12-02", -12.34, "Baker's card payment");
+        database.create_transaction("2024-12-03", 100.0, "");
+
+        let transactions = database.get_transactions();
+        assert_eq!(2, transactions.len());
+        assert_eq!("2024-12-02", transactions[0].date);
+        assert_eq!(-12.34, transactions[0].amount);
+        assert_eq!("Baker's card payment", transactions[0].description);
+        assert_eq!(None, transactions[0].bon_id);
+
+        database.pair_transaction(1, Some(1));
+        assert_eq!(Some(1), database.get_transactions()[0].bon_id);
+        database.pair_transaction(1, None);
+        assert_eq!(None, database.get_transactions()[0].bon_id);
+    }
+
+    #[test]
+    fn training_data() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("");
+        database.create_category("food");
+        database.create_product(1, "unknown");
+        database.create_product(2, "butter");
+        database.create_entry(1, 1, 1.99);
+        database.create_entry(1, 2, 2.99);
+        database.create_entry(2, 2, 2.49);
+
+        let training_data = database.get_training_data();
+        assert_eq!(2, training_data.len());
+        assert!(
+            training_data
+                .iter()
+                .all(|(product, category)| product == "butter" && category == "food")
+        );
+    }
+
+    #[test]
+    fn report() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-11-24", 10.0);
+        database.create_bon("2024-12-24 12:12:12 +0100", 20.0);
+        database.create_bon("2024-12-28", 10.0);
+        database.create_bon("2025-01-02", 5.0);
+        database.create_bon("2025-03-02", 5.0);
+        database.create_category("food");
+        database.create_category("stuff");
+        database.create_product(1, "butter");
+        database.create_product(2, "spoon");
+        database.create_entry(1, 1, 10.0);
+        database.create_entry(2, 1, 12.0);
+        database.create_entry(2, 2, 8.0);
+        database.create_entry(3, 1, 10.0);
+        database.create_entry(4, 2, 5.0);
+        database.create_entry(5, 2, 5.0);
+
+        let reports = database.get_report(ReportPeriod::Month);
+        assert_eq!(4, reports.len());
+        assert_eq!("2024-11", reports[0].period);
+        assert_eq!(None, reports[0].change);
+        assert_eq!("2024-12", reports[1].period);
+        assert_eq!(30.0, reports[1].total);
+        assert_eq!(
+            vec![("food".to_string(), 22.0), ("stuff".to_string(), 8.0)],
+            reports[1].categories
+        );
+        assert_eq!(Some(200.0), reports[1].change);
+        assert_eq!("2025-01", reports[2].period);
+        assert_eq!(
+            Some(-83.33),
+            reports[2].change.map(|c| (c * 100.0).round() / 100.0)
+        );
+        assert_eq!("2025-03", reports[3].period);
+        assert_eq!(None, reports[3].change);
+
+        database.hide_bon(5);
+        let reports = database.get_report(ReportPeriod::Year);
+        assert_eq!(2, reports.len());
+        assert_eq!("2024", reports[0].period);
+        assert_eq!(40.0, reports[0].total);
+        assert_eq!("2025", reports[1].period);
+        assert_eq!(5.0, reports[1].total);
+        assert_eq!(Some(-87.5), reports[1].change);
+    }
+
+    #[test]
+    fn price_history() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-12-24", 2.99);
+        database.create_bon("2024-11-24", 2.49);
+        database.create_bon("2025-01-24", 3.19);
+        database.create_category("food");
+        database.create_product(1, "butter");
+        database.create_product(1, "eggs");
+        database.create_entry(1, 1, 2.99);
+        database.create_entry(2, 1, 2.49);
+        database.create_entry(3, 1, 3.19);
+        database.create_entry(3, 2, 1.99);
+        database.hide_bon(3);
+
+        let history = database.get_price_history(1);
+        assert_eq!(2, history.len());
+        assert_eq!("2024-11-24", history[0].date);
+        assert_eq!(2.49, history[0].price);
+        assert_eq!("2024-12-24", history[1].date);
+        assert_eq!(2.99, history[1].price);
+        assert!(database.get_price_history(2).is_empty());
+    }
+
+    #[test]
+    fn price_stats() {
+        let history = vec![
+            PricePoint::new("2023-01-10", 1.49),
+            PricePoint::new("2024-02-10", 1.99),
+            PricePoint::new("2024-12-24", 2.49),
+            PricePoint::new("2025-01-24", 2.29),
+        ];
+        assert_eq!(None, PriceStats::new(&[]));
+
+        let stats = PriceStats::new(&history).expect("No stats");
+        assert_eq!(1.49, stats.min);
+        assert_eq!(2.49, stats.max);
+        assert_eq!(2.29, stats.last);
+        assert_eq!(2.065, (stats.average * 1000.0).round() / 1000.0);
+        assert_eq!(53.69, (stats.change * 100.0).round() / 100.0);
+
+        assert_eq!(Some("2024-02".to_string()), month_cutoff("2025-01-24", 12));
+        assert_eq!(Some("2024-12".to_string()), month_cutoff("2024-12", 1));
+        let window = PriceStats::window(&history, 12);
+        assert_eq!(3, window.len());
+        assert_eq!("2024-02-10", window[0].date);
+        let window = PriceStats::window(&history, 2);
+        assert_eq!(2, window.len());
+        let stats = PriceStats::new(window).expect("No stats");
+        assert_eq!(-8.03, (stats.change * 100.0).round() / 100.0);
+    }
+
+    #[test]
+    fn price_alerts() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_bon("2024-11-24", 0.0);
+        database.create_bon("2024-12-24", 0.0);
+        database.create_bon("2025-01-24", 0.0);
+        database.set_bon_store(3, "market");
+        database.create_category("food");
+        database.create_product(1, "butter");
+        database.create_product(1, "eggs");
+        database.create_product(1, "milk");
+        database.create_entry(1, 1, 2.00);
+        database.create_entry(3, 1, 2.50);
+        database.create_entry(2, 1, 2.10);
+        database.create_entry(1, 2, 3.00);
+        database.create_entry(2, 2, 3.10);
+        database.create_entry(1, 3, 1.00);
+
+        assert_eq!(vec![1], database.get_price_alerts(10.0));
+        assert_eq!(vec![1, 2], database.get_price_alerts(1.0));
+        assert!(database.get_price_alerts(50.0).is_empty());
+
+        let history = database.get_price_history(1);
+        assert_eq!("market", history[2].store);
+        assert_eq!("", history[0].store);
+    }
 }
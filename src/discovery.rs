@@ -0,0 +1,292 @@
+use color_eyre::eyre::WrapErr;
+use glob::{MatchOptions, Pattern};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+const HEIF_EXTENSIONS: [&str; 2] = ["heic", "heif"];
+const IMAGE_EXTENSIONS: [&str; 8] = ["heic", "heif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+
+// the preview and the OCR can convert the same photo at the same time
+static CONVERTED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, Default)]
+pub struct Discovery {
+    pub exclude: Vec<Pattern>,
+    pub include: Vec<Pattern>,
+    pub recursive: bool,
+    pub roots: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportFile {
+    pub modified: SystemTime,
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl Discovery {
+    // oldest first, so receipts are imported in the order they were taken
+    pub fn files(&self) -> Vec<ImportFile> {
+        let mut files: Vec<ImportFile> = Vec::new();
+        self.roots
+            .iter()
+            .for_each(|root| self.visit(root, root, &mut files));
+        files.sort_by(|a, b| {
+            a.modified
+                .cmp(&b.modified)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        files
+    }
+
+    pub fn is_image(path: &Path) -> bool {
+        has_extension(path, &IMAGE_EXTENSIONS)
+    }
+
+    // patterns are matched against the path relative to its root, so "*.jpg" also matches files
+    // in subfolders and "scans/**" matches a whole subfolder
+    fn matches(&self, relative: &Path) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches_path_with(relative, options)))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches_path_with(relative, options))
+    }
+
+    pub fn new(
+        roots: &[String],
+        recursive: bool,
+        include: &[String],
+        exclude: &[String],
+    ) -> color_eyre::Result<Self> {
+        let patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Pattern::new(pattern)
+                        .wrap_err_with(|| format!("Invalid import pattern {pattern}"))
+                })
+                .collect::<color_eyre::Result<Vec<Pattern>>>()
+        };
+        Ok(Self {
+            exclude: patterns(exclude)?,
+            include: patterns(include)?,
+            recursive,
+            roots: roots.iter().map(PathBuf::from).collect(),
+        })
+    }
+
+    fn visit(&self, root: &Path, dir: &Path, files: &mut Vec<ImportFile>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if self.recursive {
+                    self.visit(root, &path, files);
+                }
+                continue;
+            }
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            if !Self::is_image(&path) || !self.matches(&relative) {
+                continue;
+            }
+            if let Ok(metadata) = fs::metadata(&path) {
+                files.push(ImportFile {
+                    modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    name: relative.to_string_lossy().into_owned(),
+                    path,
+                    size: metadata.len(),
+                });
+            }
+        }
+    }
+}
+
+// identifies a file independent of its name, so renamed or copied bons are recognised
+pub fn content_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase().as_str()))
+}
+
+// neither tesseract nor the image crate read HEIC photos, so they are converted to a temporary
+// PNG with heif-convert from libheif, all other images are decoded directly
+pub fn with_decodable<T>(path: &Path, decode: impl FnOnce(&Path) -> T) -> io::Result<T> {
+    if !has_extension(path, &HEIF_EXTENSIONS) {
+        return Ok(decode(path));
+    }
+    let converted = std::env::temp_dir().join(format!(
+        "bon-scanner-{}-{}.png",
+        std::process::id(),
+        CONVERTED.fetch_add(1, Ordering::Relaxed)
+    ));
+    let output = Command::new("heif-convert")
+        .arg(path)
+        .arg(&converted)
+        .output()
+        .map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("HEIC photos need heif-convert from libheif: {error}"),
+            )
+        })?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&converted);
+        return Err(io::Error::other(format!(
+            "Couldn't convert {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let decoded = decode(&converted);
+    fs::remove_file(&converted)?;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn create(root: &Path, name: &str, age: u64) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().expect("File has no parent"))
+            .expect("Couldn't create directory");
+        let file = File::create(&path).expect("Couldn't create file");
+        file.set_modified(SystemTime::now() - Duration::from_secs(age))
+            .expect("Couldn't set modification time");
+    }
+
+    #[test]
+    fn decodable_images() {
+        let path = Path::new("scans/bon.jpg");
+        let decoded = with_decodable(path, Path::to_path_buf).expect("Couldn't decode image");
+        assert_eq!(path, decoded);
+
+        // an empty photo can't be converted, whether heif-convert is installed or not
+        let root = std::env::temp_dir().join(format!("bon-scanner-heif-{}", std::process::id()));
+        create(&root, "bon.HEIC", 0);
+        let mut decoded = false;
+        assert!(with_decodable(&root.join("bon.HEIC"), |_| decoded = true).is_err());
+        assert!(!decoded);
+
+        fs::remove_dir_all(&root).expect("Couldn't remove test directory");
+    }
+
+    #[test]
+    fn discover_files() {
+        let root =
+            std::env::temp_dir().join(format!("bon-scanner-discovery-{}", std::process::id()));
+        let other = root.join("other");
+        let inbox = root.join("inbox");
+        create(&inbox, "IMG_1.JPG", 30);
+        create(&inbox, "IMG_10.jpg", 40);
+        create(&inbox, "notjpg.txt", 10);
+        create(&inbox, "scans/bon.webp", 20);
+        create(&inbox, "scans/bon.heic", 50);
+        create(&inbox, "skip/bon.png", 10);
+        create(&other, "bon.tiff", 60);
+
+        let names = |discovery: &Discovery| {
+            discovery
+                .files()
+                .iter()
+                .map(|file| file.name.replace('\\', "/"))
+                .collect::<Vec<String>>()
+        };
+        let roots = [
+            inbox.to_string_lossy().into_owned(),
+            other.to_string_lossy().into_owned(),
+        ];
+
+        let discovery = Discovery::new(&roots, false, &[], &[]).expect("Couldn't build discovery");
+        assert_eq!(
+            vec!["bon.tiff", "IMG_10.jpg", "IMG_1.JPG"],
+            names(&discovery)
+        );
+        let files = discovery.files();
+        assert_eq!(other.join("bon.tiff"), files[0].path);
+        assert_eq!(0, files[0].size);
+
+        let discovery = Discovery::new(&roots, true, &[], &["skip/**".to_string()])
+            .expect("Couldn't build discovery");
+        assert_eq!(
+            vec![
+                "bon.tiff",
+                "scans/bon.heic",
+                "IMG_10.jpg",
+                "IMG_1.JPG",
+                "scans/bon.webp"
+            ],
+            names(&discovery)
+        );
+
+        let discovery = Discovery::new(&roots, true, &["img_*".to_string()], &[])
+            .expect("Couldn't build discovery");
+        assert_eq!(vec!["IMG_10.jpg", "IMG_1.JPG"], names(&discovery));
+
+        assert!(Discovery::new(&roots, true, &["scans/[".to_string()], &[]).is_err());
+
+        fs::remove_dir_all(&root).expect("Couldn't remove test directory");
+    }
+
+    #[test]
+    fn hash_content() {
+        let root = std::env::temp_dir().join(format!("bon-scanner-hash-{}", std::process::id()));
+        fs::create_dir_all(&root).expect("Couldn't create directory");
+        fs::write(root.join("IMG_1.jpg"), "bon").expect("Couldn't write file");
+        fs::write(root.join("copy.jpg"), "bon").expect("Couldn't write file");
+        fs::write(root.join("other.jpg"), "other bon").expect("Couldn't write file");
+
+        let hash = content_hash(&root.join("IMG_1.jpg")).expect("Couldn't hash file");
+        assert_eq!(
+            "72b90a0d25a6e8f71d5f2ed323a8d4412fa7fb9c08c62a8c7a1810ab3176361c",
+            hash
+        );
+        assert_eq!(
+            hash,
+            content_hash(&root.join("copy.jpg")).expect("Couldn't hash file")
+        );
+        assert_ne!(
+            hash,
+            content_hash(&root.join("other.jpg")).expect("Couldn't hash file")
+        );
+        assert!(content_hash(&root.join("missing.jpg")).is_err());
+
+        fs::remove_dir_all(&root).expect("Couldn't remove test directory");
+    }
+
+    #[test]
+    fn image_extensions() {
+        assert!(Discovery::is_image(Path::new("bon.JPG")));
+        assert!(Discovery::is_image(Path::new("bon.Webp")));
+        assert!(!Discovery::is_image(Path::new("notjpg.txt")));
+        assert!(!Discovery::is_image(Path::new("jpg")));
+    }
+}
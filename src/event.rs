@@ -1,6 +1,11 @@
+use crate::scan::OcrEntry;
 use color_eyre::eyre::OptionExt;
 use futures::{FutureExt, StreamExt};
+use image::RgbImage;
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use ratatui::crossterm::event::Event as CrosstermEvent;
+use std::path::PathBuf;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
@@ -15,26 +20,58 @@ pub enum Event {
 
 #[derive(Clone, Debug)]
 pub enum AppEvent {
+    ApplyRules,
+    BackgroundScanned(String, Result<Vec<OcrEntry>, String>),
+    BatchScanned(String, Result<Vec<OcrEntry>, String>),
+    BonImageLoaded(String, Result<(RgbImage, (u32, u32)), String>),
     CalculateSummary,
     ConvertToBon,
+    GoAddEntryState,
+    GoBatchState,
     GoBlacklistState,
+    GoBonImageState,
+    GoBudgetsState,
     GoCategoryState,
+    GoChartsState,
     GoConvertBonState,
+    GoDeleteCategoryState,
+    GoDuplicateBonState,
     GoEditBonPriceState,
+    GoEditBudgetState,
+    GoEditCategoryColorState,
     GoEditCategoryState,
+    GoEditDateState,
     GoEditNameState,
+    GoEditOcrLineState,
     GoEditPriceState,
+    GoEditRuleState,
+    GoEditStoreState,
+    GoExportCsvState,
     GoHomeState,
     GoImportState,
+    GoImportStatementState,
+    GoInsertOcrLineState,
+    GoMergeCategoryState,
+    GoMoveCategoryState,
     GoOcrState,
+    GoPriceHistoryState,
+    GoReconcileState,
+    GoRenameCategoryState,
+    GoReportsState,
+    GoRuleCategoryState,
+    GoRulesState,
+    GoSplitOcrLineState,
     HideItem,
     ImportBon,
+    ImportFileReady(String),
     NextItem,
     OcrMarkDate,
     OcrMarkSum,
     PerformOCR,
     PreviousItem,
+    StartBatch,
     UpdateFromDatabase,
+    UpdateImportList,
     Quit,
 }
 
@@ -68,6 +105,65 @@ impl EventHandler {
     pub fn send(&mut self, app_event: AppEvent) {
         let _ = self.sender.send(Event::App(app_event));
     }
+
+    // runs blocking work like OCR off the UI loop, the task reports back through app events
+    pub fn spawn_blocking<F>(&self, task: F)
+    where
+        F: FnOnce(&dyn Fn(AppEvent)) + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            task(&|app_event| {
+                let _ = sender.send(Event::App(app_event));
+            })
+        });
+    }
+
+    // the watcher stops when it's dropped, so the caller has to keep it
+    pub fn watch(&self, paths: &[PathBuf], recursive: bool) -> notify::Result<RecommendedWatcher> {
+        let sender = self.sender.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+                let Ok(event) = result else {
+                    return;
+                };
+                // a file is complete once it was closed after writing or moved into the folder
+                let ready: Vec<&PathBuf> = match event.kind {
+                    EventKind::Access(AccessKind::Close(AccessMode::Write))
+                    | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                        event.paths.iter().collect()
+                    }
+                    // renames inside the folder report the old and the new path
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                        event.paths.last().into_iter().collect()
+                    }
+                    _ => Vec::new(),
+                };
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Modify(ModifyKind::Name(_))
+                        | EventKind::Remove(_)
+                ) {
+                    let _ = sender.send(Event::App(AppEvent::UpdateImportList));
+                }
+                ready.iter().for_each(|path| {
+                    let _ = sender.send(Event::App(AppEvent::ImportFileReady(
+                        path.to_string_lossy().into_owned(),
+                    )));
+                });
+            })?;
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        // a missing import folder shouldn't stop watching the others
+        paths.iter().for_each(|path| {
+            let _ = watcher.watch(path, mode);
+        });
+        Ok(watcher)
+    }
 }
 
 struct EventTask {
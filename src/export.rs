@@ -0,0 +1,444 @@
+use crate::database::{Bon, Category, Database, day};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    Beancount,
+    Hledger,
+    Ledger,
+}
+
+impl Dialect {
+    pub fn extension(&self) -> &str {
+        match self {
+            Dialect::Beancount => "beancount",
+            Dialect::Hledger => "journal",
+            Dialect::Ledger => "ledger",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "beancount" => Some(Dialect::Beancount),
+            "hledger" => Some(Dialect::Hledger),
+            "ledger" => Some(Dialect::Ledger),
+            _ => None,
+        }
+    }
+
+    fn account(&self, segments: &[&str]) -> String {
+        match self {
+            // beancount account segments have to start with a capital letter or digit and
+            // must not contain whitespace
+            Dialect::Beancount => segments
+                .iter()
+                .map(|segment| {
+                    segment
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                        .map(|word| {
+                            let mut chars = word.chars();
+                            chars
+                                .next()
+                                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                                .unwrap_or_default()
+                        })
+                        .collect::<Vec<String>>()
+                        .join("-")
+                })
+                .collect::<Vec<String>>()
+                .join(":"),
+            // two spaces separate the account from the amount, so collapse them
+            Dialect::Hledger | Dialect::Ledger => segments
+                .iter()
+                .map(|segment| segment.split_whitespace().collect::<Vec<&str>>().join(" "))
+                .collect::<Vec<String>>()
+                .join(":"),
+        }
+    }
+
+    fn amount(&self, amount: f64) -> String {
+        match self {
+            Dialect::Beancount => format!("{:.2} EUR", amount),
+            Dialect::Hledger | Dialect::Ledger => format!("{:.2} €", amount),
+        }
+    }
+
+    fn header(&self, bon: &Bon) -> String {
+        let payee = if bon.store.is_empty() {
+            format!("Bon {}", bon.bon_id)
+        } else {
+            bon.store.clone()
+        };
+        match self {
+            Dialect::Beancount if bon.store.is_empty() => {
+                format!("{} * \"Bon {}\"", day(&bon.date), bon.bon_id)
+            }
+            Dialect::Beancount => format!(
+                "{} * \"{}\" \"Bon {}\"",
+                day(&bon.date),
+                bon.store.replace('"', "'"),
+                bon.bon_id
+            ),
+            Dialect::Hledger => format!("{} {}  ; bon:{}", day(&bon.date), payee, bon.bon_id),
+            Dialect::Ledger => format!(
+                "{} {}  ; bon: {}",
+                day(&bon.date).replace('-', "/"),
+                payee,
+                bon.bon_id
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ExportFilter {
+    pub category: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl ExportFilter {
+    fn contains_date(&self, date: &str) -> bool {
+        let date = day(date);
+        self.from.as_ref().is_none_or(|from| date >= from.as_str())
+            && self.to.as_ref().is_none_or(|to| date <= to.as_str())
+    }
+
+    fn matches_category(&self, categories: &[Category], category: &str) -> bool {
+        let Some(filter) = &self.category else {
+            return true;
+        };
+        if filter == category {
+            return true;
+        }
+        let ancestor = categories.iter().find(|elem| &elem.category == filter);
+        let current = categories.iter().find(|elem| elem.category == category);
+        if let Some(ancestor) = ancestor
+            && let Some(current) = current
+        {
+            Category::is_descendant_of(categories, current.category_id, ancestor.category_id)
+        } else {
+            false
+        }
+    }
+
+    // accepts "from..to category", where every part is optional
+    pub fn parse(input: &str) -> Self {
+        let mut filter = Self::default();
+        let mut category: Vec<&str> = Vec::new();
+        for token in input.split_whitespace() {
+            if let Some((from, to)) = token.split_once("..") {
+                filter.from = Some(from.to_string()).filter(|from| !from.is_empty());
+                filter.to = Some(to.to_string()).filter(|to| !to.is_empty());
+            } else {
+                category.push(token);
+            }
+        }
+        if !category.is_empty() {
+            filter.category = Some(category.join(" "));
+        }
+        filter
+    }
+}
+
+pub fn bons_csv(bons: &[Bon], categories: &[Category], filter: &ExportFilter) -> String {
+    let mut csv = String::from("id,date,store,total\r\n");
+    bons.iter()
+        .filter(|bon| filter.contains_date(&bon.date))
+        .filter(|bon| {
+            filter.category.is_none()
+                || bon
+                    .entries
+                    .iter()
+                    .any(|entry| filter.matches_category(categories, &entry.category))
+        })
+        .for_each(|bon| {
+            csv.push_str(&format!(
+                "{},{},{},{:.2}\r\n",
+                bon.bon_id,
+                quote(&bon.date),
+                quote(&bon.store),
+                bon.price
+            ))
+        });
+    csv
+}
+
+pub fn entries_csv(bons: &[Bon], categories: &[Category], filter: &ExportFilter) -> String {
+    let mut csv = String::from("bon_id,date,category,product,price\r\n");
+    bons.iter()
+        .filter(|bon| filter.contains_date(&bon.date))
+        .for_each(|bon| {
+            bon.entries
+                .iter()
+                .filter(|entry| filter.matches_category(categories, &entry.category))
+                .for_each(|entry| {
+                    csv.push_str(&format!(
+                        "{},{},{},{},{:.2}\r\n",
+                        bon.bon_id,
+                        quote(&bon.date),
+                        quote(&entry.category),
+                        quote(&entry.product),
+                        entry.price
+                    ))
+                })
+        });
+    csv
+}
+
+pub fn journal(
+    bons: &[Bon],
+    categories: &[Category],
+    filter: &ExportFilter,
+    dialect: Dialect,
+    funding_account: &str,
+) -> String {
+    let funding_segments: Vec<&str> = funding_account.split(':').collect();
+    let funding_account = dialect.account(&funding_segments);
+    let mut accounts: Vec<String> = vec![funding_account.clone()];
+    let mut transactions: Vec<String> = Vec::new();
+    let mut bons_in_range: Vec<&Bon> = bons
+        .iter()
+        .filter(|bon| filter.contains_date(&bon.date))
+        .collect();
+    bons_in_range.sort_by(|a, b| a.date.cmp(&b.date).then(a.bon_id.cmp(&b.bon_id)));
+    for bon in bons_in_range.iter() {
+        let mut postings: Vec<(String, f64)> = Vec::new();
+        bon.entries
+            .iter()
+            .filter(|entry| filter.matches_category(categories, &entry.category))
+            .for_each(|entry| {
+                let path = Category::path(categories, &entry.category);
+                let mut segments = vec!["expenses"];
+                if path.is_empty() {
+                    segments.push(if entry.category.is_empty() {
+                        "uncategorised"
+                    } else {
+                        entry.category.as_str()
+                    });
+                } else {
+                    segments.extend(path.iter().map(|category| category.category.as_str()));
+                }
+                let account = dialect.account(&segments);
+                match postings.iter_mut().find(|(elem, _)| *elem == account) {
+                    Some(posting) => posting.1 += entry.price,
+                    None => postings.push((account, entry.price)),
+                }
+            });
+        if postings.is_empty() {
+            continue;
+        }
+        postings.iter().for_each(|(account, _)| {
+            if !accounts.contains(account) {
+                accounts.push(account.clone());
+            }
+        });
+        let mut transaction = dialect.header(bon);
+        let width = postings
+            .iter()
+            .map(|(account, _)| account.chars().count())
+            .max()
+            .unwrap_or(0);
+        postings.iter().for_each(|(account, amount)| {
+            transaction.push_str(&format!(
+                "\n    {:width$}  {}",
+                account,
+                dialect.amount(*amount)
+            ));
+        });
+        transaction.push_str(&format!("\n    {}\n", funding_account));
+        transactions.push(transaction);
+    }
+    // beancount only accepts postings to accounts that were opened before
+    if dialect == Dialect::Beancount
+        && let Some(first) = bons_in_range.first()
+    {
+        accounts.sort();
+        let open = accounts
+            .iter()
+            .map(|account| format!("{} open {}\n", day(&first.date), account))
+            .collect::<String>();
+        transactions.insert(0, open);
+    }
+    transactions.join("\n")
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn write_csv(database: &Database, path: &Path, filter: &ExportFilter) -> std::io::Result<()> {
+    let bons = database.get_bons();
+    let categories = database.get_categories();
+    fs::create_dir_all(path)?;
+    fs::write(path.join("bons.csv"), bons_csv(&bons, &categories, filter))?;
+    fs::write(
+        path.join("entries.csv"),
+        entries_csv(&bons, &categories, filter),
+    )
+}
+
+pub fn write_journal(
+    database: &Database,
+    file: &Path,
+    filter: &ExportFilter,
+    dialect: Dialect,
+    funding_account: &str,
+) -> std::io::Result<()> {
+    let bons = database.get_bons();
+    let categories = database.get_categories();
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        file,
+        journal(&bons, &categories, filter, dialect, funding_account),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Entry;
+
+    fn database() -> Database {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("food");
+        database.create_category("dairy");
+        database.create_category("stuff");
+        database.set_category_parent(2, Some(1));
+        database.create_product(1, "Bread, whole grain");
+        database.create_product(2, "Milk \"fresh\"");
+        database.create_product(3, "Spoon");
+        database.create_bon("2024-12-01", 5.5);
+        database.create_bon("2025-01-24", 2.0);
+        database.set_bon_store(1, "Corner Shop");
+        database.create_entry(1, 1, 3.0);
+        database.create_entry(1, 3, 2.5);
+        database.create_entry(2, 2, 2.0);
+        database
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!("plain", quote("plain"));
+        assert_eq!("\"a,b\"", quote("a,b"));
+        assert_eq!("\"say \"\"hi\"\"\"", quote("say \"hi\""));
+        assert_eq!("\"two\r\nlines\"", quote("two\r\nlines"));
+    }
+
+    #[test]
+    fn parse_filter() {
+        assert_eq!(ExportFilter::default(), ExportFilter::parse(""));
+        let filter = ExportFilter::parse("2024-01-01..2024-12-31 pet food");
+        assert_eq!(Some("2024-01-01".to_string()), filter.from);
+        assert_eq!(Some("2024-12-31".to_string()), filter.to);
+        assert_eq!(Some("pet food".to_string()), filter.category);
+        let filter = ExportFilter::parse("..2024-12-31");
+        assert_eq!(None, filter.from);
+        assert_eq!(Some("2024-12-31".to_string()), filter.to);
+    }
+
+    #[test]
+    fn export_bons() {
+        let database = database();
+        let bons = database.get_bons();
+        let categories = database.get_categories();
+        assert_eq!(
+            "id,date,store,total\r\n1,2024-12-01,Corner Shop,5.50\r\n2,2025-01-24,,2.00\r\n",
+            bons_csv(&bons, &categories, &ExportFilter::default())
+        );
+        let filter = ExportFilter::parse("2025-01-01..");
+        assert_eq!(
+            "id,date,store,total\r\n2,2025-01-24,,2.00\r\n",
+            bons_csv(&bons, &categories, &filter)
+        );
+        let filter = ExportFilter::parse("stuff");
+        assert_eq!(
+            "id,date,store,total\r\n1,2024-12-01,Corner Shop,5.50\r\n",
+            bons_csv(&bons, &categories, &filter)
+        );
+    }
+
+    #[test]
+    fn export_entries() {
+        let database = database();
+        let bons = database.get_bons();
+        let categories = database.get_categories();
+        assert_eq!(
+            "bon_id,date,category,product,price\r\n\
+             1,2024-12-01,food,\"Bread, whole grain\",3.00\r\n\
+             1,2024-12-01,stuff,Spoon,2.50\r\n\
+             2,2025-01-24,dairy,\"Milk \"\"fresh\"\"\",2.00\r\n",
+            entries_csv(&bons, &categories, &ExportFilter::default())
+        );
+        let filter = ExportFilter::parse("food");
+        assert_eq!(
+            "bon_id,date,category,product,price\r\n\
+             1,2024-12-01,food,\"Bread, whole grain\",3.00\r\n\
+             2,2025-01-24,dairy,\"Milk \"\"fresh\"\"\",2.00\r\n",
+            entries_csv(&bons, &categories, &filter)
+        );
+        let filter = ExportFilter::parse("..2024-12-31 food");
+        assert_eq!(
+            "bon_id,date,category,product,price\r\n\
+             1,2024-12-01,food,\"Bread, whole grain\",3.00\r\n",
+            entries_csv(&bons, &categories, &filter)
+        );
+    }
+
+    #[test]
+    fn account_names() {
+        assert_eq!(
+            "expenses:pet food:dairy",
+            Dialect::Hledger.account(&["expenses", "pet  food", "dairy"])
+        );
+        assert_eq!(
+            "Expenses:Pet-Food:Dairy",
+            Dialect::Beancount.account(&["expenses", "pet food", "dairy"])
+        );
+        assert_eq!(
+            "Assets:Bank-Giro",
+            Dialect::Beancount.account(&["assets", "bank giro"])
+        );
+        assert_eq!(Some(Dialect::Ledger), Dialect::parse("Ledger"));
+        assert_eq!(None, Dialect::parse("gnucash"));
+    }
+
+    #[test]
+    fn export_journals() {
+        let database = database();
+        let bons = database.get_bons();
+        let categories = database.get_categories();
+        let filter = ExportFilter::default();
+        for (dialect, golden) in [
+            (
+                Dialect::Beancount,
+                include_str!("../testdata/bons.beancount"),
+            ),
+            (Dialect::Hledger, include_str!("../testdata/bons.journal")),
+            (Dialect::Ledger, include_str!("../testdata/bons.ledger")),
+        ] {
+            assert_eq!(
+                golden,
+                journal(&bons, &categories, &filter, dialect, "assets:cash")
+            );
+        }
+    }
+
+    #[test]
+    fn timestamped_dates() {
+        let mut bon = Bon::new("2024-12-24 12:12:12 +0100", 2.0);
+        bon.entries.push(Entry::new("", "Milk", 2.0));
+        let filter = ExportFilter::parse("2024-12-24..2024-12-24");
+        let ledger = journal(&[bon], &[], &filter, Dialect::Ledger, "assets:cash");
+        assert!(ledger.starts_with("2024/12/24 Bon 0  ; bon: 0\n"));
+    }
+}
@@ -0,0 +1,217 @@
+pub trait Editable {
+    type Header;
+    type Item;
+
+    fn header(&self) -> Self::Header;
+    fn items(&mut self) -> &mut Vec<Self::Item>;
+    fn select(&mut self, index: Option<usize>);
+    fn set_header(&mut self, header: Self::Header);
+}
+
+pub enum Change<E: Editable> {
+    Header(E::Header, E::Header),
+    Insert(usize, E::Item),
+    Remove(usize, E::Item),
+    Replace(usize, E::Item, E::Item),
+    Swap(usize, usize),
+}
+
+impl<E: Editable> Change<E>
+where
+    E::Header: Clone,
+    E::Item: Clone,
+{
+    fn apply(&self, target: &mut E) {
+        let len = target.items().len();
+        match self {
+            Change::Header(_, after) => target.set_header(after.clone()),
+            Change::Insert(i, item) if *i <= len => {
+                target.items().insert(*i, item.clone());
+                target.select(Some(*i));
+            }
+            Change::Remove(i, _) if *i < len => {
+                target.items().remove(*i);
+                target.select(if len > 1 {
+                    Some((*i).min(len - 2))
+                } else {
+                    None
+                });
+            }
+            Change::Replace(i, _, after) if *i < len => {
+                target.items()[*i] = after.clone();
+                target.select(Some(*i));
+            }
+            Change::Swap(i, j) if *i < len && *j < len => {
+                target.items().swap(*i, *j);
+                target.select(Some(*j));
+            }
+            _ => {}
+        }
+    }
+
+    fn revert(&self, target: &mut E) {
+        let len = target.items().len();
+        match self {
+            Change::Header(before, _) => target.set_header(before.clone()),
+            Change::Insert(i, _) if *i < len => {
+                target.items().remove(*i);
+                target.select(if len > 1 {
+                    Some(i.saturating_sub(1))
+                } else {
+                    None
+                });
+            }
+            Change::Remove(i, item) if *i <= len => {
+                target.items().insert(*i, item.clone());
+                target.select(Some(*i));
+            }
+            Change::Replace(i, before, _) if *i < len => {
+                target.items()[*i] = before.clone();
+                target.select(Some(*i));
+            }
+            Change::Swap(i, j) if *i < len && *j < len => {
+                target.items().swap(*i, *j);
+                target.select(Some(*i));
+            }
+            _ => {}
+        }
+    }
+}
+
+// every edit of a list goes through record, so it can be undone and redone as one step
+pub struct History<E: Editable> {
+    redo: Vec<Vec<Change<E>>>,
+    undo: Vec<Vec<Change<E>>>,
+}
+
+impl<E: Editable> Default for History<E> {
+    fn default() -> Self {
+        Self {
+            redo: Vec::new(),
+            undo: Vec::new(),
+        }
+    }
+}
+
+impl<E: Editable> History<E>
+where
+    E::Header: Clone,
+    E::Item: Clone,
+{
+    pub fn clear(&mut self) {
+        self.redo.clear();
+        self.undo.clear();
+    }
+
+    pub fn record(&mut self, target: &mut E, changes: Vec<Change<E>>) {
+        if changes.is_empty() {
+            return;
+        }
+        changes.iter().for_each(|change| change.apply(target));
+        self.undo.push(changes);
+        self.redo.clear();
+    }
+
+    pub fn redo(&mut self, target: &mut E) -> bool {
+        if let Some(changes) = self.redo.pop() {
+            changes.iter().for_each(|change| change.apply(target));
+            self.undo.push(changes);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn undo(&mut self, target: &mut E) -> bool {
+        if let Some(changes) = self.undo.pop() {
+            changes
+                .iter()
+                .rev()
+                .for_each(|change| change.revert(target));
+            self.redo.push(changes);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Lines {
+        items: Vec<&'static str>,
+        selected: Option<usize>,
+        title: &'static str,
+    }
+
+    impl Editable for Lines {
+        type Header = &'static str;
+        type Item = &'static str;
+
+        fn header(&self) -> Self::Header {
+            self.title
+        }
+
+        fn items(&mut self) -> &mut Vec<Self::Item> {
+            &mut self.items
+        }
+
+        fn select(&mut self, index: Option<usize>) {
+            self.selected = index;
+        }
+
+        fn set_header(&mut self, header: Self::Header) {
+            self.title = header;
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut lines = Lines {
+            items: vec!["a", "b", "c"],
+            ..Default::default()
+        };
+        let mut history = History::default();
+
+        history.record(&mut lines, vec![Change::Remove(1, "b")]);
+        assert_eq!(vec!["a", "c"], lines.items);
+        assert_eq!(Some(1), lines.selected);
+
+        history.record(
+            &mut lines,
+            vec![Change::Replace(0, "a", "a c"), Change::Remove(1, "c")],
+        );
+        history.record(&mut lines, vec![Change::Header("", "bon")]);
+        assert_eq!(vec!["a c"], lines.items);
+        assert_eq!("bon", lines.title);
+
+        assert!(history.undo(&mut lines));
+        assert_eq!("", lines.title);
+        assert!(history.undo(&mut lines));
+        assert_eq!(vec!["a", "c"], lines.items);
+        assert!(history.undo(&mut lines));
+        assert_eq!(vec!["a", "b", "c"], lines.items);
+        assert_eq!(Some(1), lines.selected);
+        assert!(!history.undo(&mut lines));
+
+        assert!(history.redo(&mut lines));
+        assert_eq!(vec!["a", "c"], lines.items);
+
+        // a new edit drops what could be redone
+        history.record(&mut lines, vec![Change::Swap(0, 1)]);
+        assert_eq!(vec!["c", "a"], lines.items);
+        assert_eq!(Some(1), lines.selected);
+        assert!(!history.redo(&mut lines));
+        assert!(history.undo(&mut lines));
+        assert_eq!(vec!["a", "c"], lines.items);
+        assert_eq!(Some(0), lines.selected);
+
+        history.record(&mut lines, vec![Change::Insert(2, "d")]);
+        assert_eq!(vec!["a", "c", "d"], lines.items);
+        history.clear();
+        assert!(!history.undo(&mut lines));
+    }
+}
@@ -1,14 +1,29 @@
+use clap::Parser;
+
 pub mod app;
+pub mod backup;
+pub mod bank;
+pub mod classifier;
+pub mod cli;
 pub mod database;
+pub mod discovery;
 pub mod event;
+pub mod export;
+pub mod history;
+pub mod scan;
 pub mod settings;
 pub mod ui;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
+    let cli = cli::Cli::parse();
+    let settings = cli.settings()?;
+    if let Some(command) = cli.command {
+        return cli::run(command, &settings);
+    }
     let terminal = ratatui::init();
-    let result = app::App::new().run(terminal).await;
+    let result = app::App::with_settings(settings).run(terminal).await;
     ratatui::restore();
     result
 }
@@ -0,0 +1,961 @@
+use crate::database::{self, Database};
+use crate::discovery;
+use float_cmp::{ApproxEq, F64Margin};
+use regex::Regex;
+use rusty_tesseract::{Args, Data, Image, TessError};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use textdistance::str::damerau_levenshtein;
+
+const SUM_KEYWORDS: [&str; 5] = ["summe", "gesamt", "total", "zu zahlen", "betrag"];
+
+#[derive(Default)]
+pub struct BonDraft {
+    pub date: String,
+    pub items: Vec<database::Entry>,
+    pub price: f64,
+    pub store: String,
+}
+
+impl BonDraft {
+    // photographing a bon twice gives slightly different OCR results, so the entries only have to
+    // be similar
+    pub fn find_duplicate<'a>(&self, bons: &'a [database::Bon]) -> Option<&'a database::Bon> {
+        let date = self.iso_date();
+        bons.iter().find(|bon| {
+            bon.date == date
+                && bon.price.approx_eq(
+                    self.price,
+                    F64Margin {
+                        ulps: 2,
+                        epsilon: 0.005,
+                    },
+                )
+                && similar_entries(&self.items, &bon.entries)
+        })
+    }
+
+    pub fn iso_date(&self) -> String {
+        let mut split = self.date.split(".").collect::<Vec<&str>>();
+        split.reverse();
+        split.join("-")
+    }
+
+    pub fn items_total(&self) -> f64 {
+        self.items
+            .iter()
+            .fold(0.0, |total, entry| total + entry.price)
+    }
+
+    pub fn price_matches(&self) -> bool {
+        self.price.approx_eq(
+            self.items_total(),
+            F64Margin {
+                ulps: 2,
+                epsilon: 1.0,
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OcrEntry {
+    pub name: String,
+    pub ocr_type: OcrType,
+    pub region: Option<Region>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OcrType {
+    Date,
+    Entry,
+    Sum,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PostImport {
+    Delete,
+    #[default]
+    Keep,
+    Move,
+    Rename,
+}
+
+impl PostImport {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "delete" => Some(PostImport::Delete),
+            "keep" => Some(PostImport::Keep),
+            "move" => Some(PostImport::Move),
+            "rename" => Some(PostImport::Rename),
+            _ => None,
+        }
+    }
+}
+
+// runs the post-import action on the image of an imported bon and remembers where it ended up
+pub fn archive(
+    database: &Database,
+    bon_id: i64,
+    draft: &BonDraft,
+    file: &str,
+    action: PostImport,
+    archive_path: &str,
+) -> io::Result<Option<PathBuf>> {
+    let path = Path::new(file);
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let target = match action {
+        PostImport::Delete => {
+            fs::remove_file(path)?;
+            return Ok(None);
+        }
+        PostImport::Keep => return Ok(Some(path.to_path_buf())),
+        PostImport::Move => {
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            free_path(Path::new(archive_path), &stem, &extension)
+        }
+        PostImport::Rename => {
+            let stem = format!("{} {}", draft.iso_date(), draft.store)
+                .trim()
+                .chars()
+                .map(|c| {
+                    if c.is_alphanumeric() || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>();
+            free_path(path.parent().unwrap_or(Path::new("")), &stem, &extension)
+        }
+    };
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // rename doesn't work across file systems
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target)?;
+        fs::remove_file(path)?;
+    }
+    database.set_processed_archive(bon_id, &processed_path(&target));
+    Ok(Some(target))
+}
+
+// bounding box of a line in pixels of the original image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn union(&self, other: &Region) -> Region {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = (self.left + self.width).max(other.left + other.width);
+        let bottom = (self.top + self.height).max(other.top + other.height);
+        Region {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+// the category of a known product, or the category of the first rule matching a new one
+fn categorize(
+    categories: &[database::Category],
+    rules: &[database::Rule],
+    category_id: Option<i64>,
+    product: &str,
+) -> (String, Option<String>) {
+    let mut db_category = category_id.and_then(|category_id| {
+        categories
+            .iter()
+            .find(|category| category.category_id == category_id)
+    });
+    if db_category.is_none_or(|category| category.category.is_empty())
+        && let Some(category_id) = database::Rule::find_category(rules, product)
+    {
+        db_category = categories
+            .iter()
+            .find(|category| category.category_id == category_id);
+    }
+    let category = db_category
+        .map(|category| category.category.clone())
+        .unwrap_or_default();
+    let color = db_category.and_then(|category| category.color.clone());
+    (category, color)
+}
+
+// known products starting with the typed text come first, then those containing it
+pub fn complete_product(products: &[database::Product], text: &str) -> Vec<String> {
+    let text = text.trim().to_lowercase();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut starting: Vec<String> = Vec::new();
+    let mut containing: Vec<String> = Vec::new();
+    products.iter().for_each(|product| {
+        let name = product.product.to_lowercase();
+        if name.starts_with(&text) {
+            starting.push(product.product.clone());
+        } else if name.contains(&text) {
+            containing.push(product.product.clone());
+        }
+    });
+    starting.sort_by_key(|product| product.to_lowercase());
+    containing.sort_by_key(|product| product.to_lowercase());
+    starting.append(&mut containing);
+    starting.dedup();
+    starting
+}
+
+pub fn convert(database: &Database, entries: &[OcrEntry]) -> BonDraft {
+    let mut draft = BonDraft::default();
+    let db_rules = database.get_rules();
+    let db_products = database.get_products();
+    let db_categories = database.get_categories();
+    entries.iter().for_each(|elem| match elem.ocr_type {
+        OcrType::Date => {
+            if let Some(date) = extract_date(&elem.name) {
+                draft.date = date;
+            }
+        }
+        OcrType::Entry => {
+            if let Some(name) = extract_name(&elem.name)
+                && let Some(price) = extract_price(&elem.name)
+            {
+                let db_product = db_products
+                    .iter()
+                    .min_by_key(|elem| damerau_levenshtein(&name, &elem.product));
+                let distance = if let Some(product) = &db_product {
+                    damerau_levenshtein(&name, &product.product)
+                } else {
+                    usize::MAX
+                };
+                let mut category_id = None;
+                let mut product = name;
+                if distance < 4 {
+                    let db_product = db_product.unwrap();
+                    product = db_product.product.clone();
+                    category_id = Some(db_product.category_id);
+                }
+                let (category, color) =
+                    categorize(&db_categories, &db_rules, category_id, &product);
+                draft.items.push(database::Entry {
+                    category,
+                    color,
+                    product,
+                    price,
+                });
+            }
+        }
+        OcrType::Sum => {
+            if let Some(sum) = extract_price(&elem.name) {
+                draft.price = sum;
+            }
+        }
+    });
+    draft
+}
+
+// marks the first line with a date and the first line with a sum keyword, unless the user
+// already marked them
+pub fn detect(entries: &mut [OcrEntry]) {
+    if !entries
+        .iter()
+        .any(|entry| matches!(entry.ocr_type, OcrType::Date))
+        && let Some(entry) = entries
+            .iter_mut()
+            .find(|entry| extract_date(&entry.name).is_some())
+    {
+        entry.ocr_type = OcrType::Date;
+    }
+    if !entries
+        .iter()
+        .any(|entry| matches!(entry.ocr_type, OcrType::Sum))
+        && let Some(entry) = entries.iter_mut().find(|entry| {
+            matches!(entry.ocr_type, OcrType::Entry)
+                && SUM_KEYWORDS
+                    .iter()
+                    .any(|keyword| entry.name.to_lowercase().contains(keyword))
+                && extract_price(&entry.name).is_some()
+        })
+    {
+        entry.ocr_type = OcrType::Sum;
+    }
+}
+
+fn free_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+    let file_name = |suffix: String| {
+        if extension.is_empty() {
+            format!("{stem}{suffix}")
+        } else {
+            format!("{stem}{suffix}.{extension}")
+        }
+    };
+    let mut path = dir.join(file_name(String::new()));
+    let mut counter = 2;
+    while path.exists() {
+        path = dir.join(file_name(format!("_{counter}")));
+        counter += 1;
+    }
+    path
+}
+
+// tesseract reports single words, the words of a line follow each other
+fn group_lines(data: &[Data]) -> Vec<(String, Region)> {
+    let mut lines: Vec<(String, Region)> = Vec::new();
+    let mut last_line = None;
+    data.iter()
+        .filter(|word| word.level == 5 && !word.text.trim().is_empty())
+        .for_each(|word| {
+            let line = (word.page_num, word.block_num, word.par_num, word.line_num);
+            let region = Region {
+                left: word.left.max(0) as u32,
+                top: word.top.max(0) as u32,
+                width: word.width.max(0) as u32,
+                height: word.height.max(0) as u32,
+            };
+            match lines.last_mut() {
+                Some((text, line_region)) if last_line == Some(line) => {
+                    text.push(' ');
+                    text.push_str(word.text.trim());
+                    *line_region = line_region.union(&region);
+                }
+                _ => lines.push((word.text.trim().to_string(), region)),
+            }
+            last_line = Some(line);
+        });
+    lines
+}
+
+pub fn extract_date(line: &str) -> Option<String> {
+    let re = Regex::new(r"\d{2}[\.,]\d{2}[\.,]\d{4}").expect("Could not compile regex");
+    re.find(line).map(|m| m.as_str().replace(',', "."))
+}
+
+fn extract_name(line: &str) -> Option<String> {
+    let name = line.rsplit_once(' ');
+    name.map(|name| name.0.to_string())
+}
+
+fn extract_price(line: &str) -> Option<f64> {
+    let re = Regex::new(r"(\d+[.,]\d+)").expect("Could not compile regex");
+    re.find(line)
+        .and_then(|m| m.as_str().replace(',', ".").parse::<f64>().ok())
+}
+
+pub fn import(database: &Database, draft: &BonDraft, file: &str) -> i64 {
+    database.create_bon(draft.iso_date().as_str(), draft.price);
+    let bon_id = database.get_last_bon_id();
+    database.set_bon_store(bon_id, draft.store.as_str());
+    draft.items.iter().for_each(|entry| {
+        let categories = database.get_categories();
+        let category_id = categories
+            .iter()
+            .find(|cat| cat.category == entry.category)
+            .map_or_else(
+                || {
+                    database.create_category(entry.category.as_str());
+                    database.get_last_category_id()
+                },
+                |cat| cat.category_id,
+            );
+        let products = database.get_products();
+        let product_id = products
+            .iter()
+            .find(|prod| prod.product == entry.product)
+            .map_or_else(
+                || {
+                    database.create_product(category_id, entry.product.as_str());
+                    database.get_last_product_id()
+                },
+                |prod| prod.product_id,
+            );
+        database.create_entry(bon_id, product_id, entry.price);
+    });
+    let path = Path::new(file);
+    if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+        let hash = discovery::content_hash(path).unwrap_or_default();
+        database.add_processed_file(file_name, &processed_path(path), &hash, bon_id);
+    }
+    bon_id
+}
+
+// a copied or renamed photo has a different path but the same content
+pub fn identical_file(path: &Path, processed: &[database::Processed]) -> Option<String> {
+    let hash = discovery::content_hash(path).ok()?;
+    processed
+        .iter()
+        .find(|processed| processed.hash == hash)
+        .map(|processed| match processed.bon_id {
+            Some(bon_id) => format!(
+                "identical to {}, imported as bon {}",
+                processed.processed_entry, bon_id
+            ),
+            None => format!("identical to {}", processed.processed_entry),
+        })
+}
+
+// archived and renamed bons are known under their new path as well
+pub fn is_processed(path: &Path, processed: &[database::Processed]) -> bool {
+    let full_path = processed_path(path);
+    let name = path.file_name().and_then(|name| name.to_str());
+    // older records only know the file name
+    processed.iter().any(|elem| {
+        if elem.path.is_empty() {
+            name == Some(elem.processed_entry.as_str())
+        } else {
+            elem.path == full_path || elem.archive_path.as_ref() == Some(&full_path)
+        }
+    })
+}
+
+// the same file, a copy of it or another photo of the same bon
+pub fn previous_import(database: &Database, draft: &BonDraft, path: &Path) -> Option<String> {
+    let processed = database.get_processed_files();
+    if is_processed(path, &processed) {
+        return Some("already imported".to_string());
+    }
+    identical_file(path, &processed).or_else(|| {
+        draft
+            .find_duplicate(&database.get_bons())
+            .map(|bon| format!("same as bon {} from {}", bon.bon_id, bon.date))
+    })
+}
+
+// an entry typed in by hand, a known product keeps its spelling and category
+pub fn manual_entry(database: &Database, product: &str, price: f64) -> database::Entry {
+    let product = product.trim();
+    let db_product = database
+        .get_products()
+        .into_iter()
+        .find(|db_product| db_product.product.to_lowercase() == product.to_lowercase());
+    let category_id = db_product.as_ref().map(|db_product| db_product.category_id);
+    let product = db_product.map_or_else(|| product.to_string(), |db_product| db_product.product);
+    let (category, color) = categorize(
+        &database.get_categories(),
+        &database.get_rules(),
+        category_id,
+        &product,
+    );
+    database::Entry {
+        category,
+        color,
+        product,
+        price,
+    }
+}
+
+// joins a line with the one below, e.g. when the price ended up on a line of its own
+pub fn merge_lines(entries: &mut Vec<OcrEntry>, i: usize) -> bool {
+    if i + 1 >= entries.len() {
+        return false;
+    }
+    let next = entries.remove(i + 1);
+    let entry = &mut entries[i];
+    entry.name = format!("{} {}", entry.name, next.name);
+    entry.region = match (entry.region, next.region) {
+        (Some(region), Some(next)) => Some(region.union(&next)),
+        (region, next) => region.or(next),
+    };
+    if matches!(entry.ocr_type, OcrType::Entry) {
+        entry.ocr_type = next.ocr_type;
+    }
+    true
+}
+
+pub fn ocr(file: &str, blacklist: &[String]) -> Result<Vec<OcrEntry>, TessError> {
+    let args = Args {
+        lang: "deu".to_string(),
+        config_variables: HashMap::from([(
+            "tessedit_char_whitelist".into(),
+            "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZöäüÖÄÜß1234567890., &-%$@€:"
+                .into(),
+        )]),
+        dpi: Some(150),
+        psm: Some(6),
+        oem: Some(3),
+    };
+
+    let ocr_data = discovery::with_decodable(Path::new(file), |path| {
+        rusty_tesseract::image_to_data(&Image::from_path(path)?, &args)
+    })
+    .map_err(|error| TessError::CommandExitStatusError(error.to_string(), String::new()))??;
+
+    Ok(group_lines(&ocr_data.data)
+        .into_iter()
+        .map(|(line, region)| (line.trim().to_string(), region))
+        .filter(|(line, _)| line.len() > 1)
+        .map(|(line, region)| {
+            // delete the last element, when it's a single character
+            let re = Regex::new(r" \w$").expect("Could not compile regex");
+            if let Some(found) = re.find(&line) {
+                (line[..found.start()].to_string(), region)
+            } else {
+                (line.to_string(), region)
+            }
+        })
+        .filter(|(line, _)| {
+            // the last element of the line must contain a digit
+            let elems = line.split(" ").collect::<Vec<&str>>();
+            let re = Regex::new(r"\d").expect("Could not compile regex");
+            re.is_match(elems[elems.len() - 1])
+        })
+        .filter(|(line, _)| {
+            // the line must contain some sort of delimiter
+            let re = Regex::new(r"[,.:-]").expect("Could not compile regex");
+            re.is_match(line)
+        })
+        .filter(|(line, _)| !blacklist.iter().any(|elem| line.contains(elem)))
+        .map(|(line, region)| OcrEntry {
+            name: line,
+            ocr_type: OcrType::Entry,
+            region: Some(region),
+        })
+        .collect::<Vec<OcrEntry>>())
+}
+
+// splits the text of a line at a character position, both halves keep the region of the line
+pub fn split_line(entries: &mut Vec<OcrEntry>, i: usize, text: &str, at: usize) -> bool {
+    let split = text
+        .char_indices()
+        .nth(at)
+        .map_or(text.len(), |(index, _)| index);
+    let (first, second) = text.split_at(split);
+    let (first, second) = (first.trim(), second.trim());
+    if i >= entries.len() || first.is_empty() || second.is_empty() {
+        return false;
+    }
+    let region = entries[i].region;
+    entries[i].name = first.to_string();
+    entries.insert(
+        i + 1,
+        OcrEntry {
+            name: second.to_string(),
+            ocr_type: OcrType::Entry,
+            region,
+        },
+    );
+    true
+}
+
+// the same file can be reached through relative and absolute paths
+pub fn processed_path(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+// two out of three entries need a counterpart with the same price and a similar name
+fn similar_entries(items: &[database::Entry], entries: &[database::Entry]) -> bool {
+    if items.is_empty() || entries.is_empty() {
+        return items.len() == entries.len();
+    }
+    let mut unmatched: Vec<&database::Entry> = entries.iter().collect();
+    let matched = items
+        .iter()
+        .filter(|item| {
+            let product = item.product.to_lowercase();
+            unmatched
+                .iter()
+                .position(|entry| {
+                    item.price.approx_eq(
+                        entry.price,
+                        F64Margin {
+                            ulps: 2,
+                            epsilon: 0.005,
+                        },
+                    ) && damerau_levenshtein(&product, &entry.product.to_lowercase()) < 4
+                })
+                .map(|i| unmatched.remove(i))
+                .is_some()
+        })
+        .count();
+    matched * 3 >= items.len().max(entries.len()) * 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(lines: &[&str]) -> Vec<OcrEntry> {
+        lines
+            .iter()
+            .map(|line| OcrEntry {
+                name: line.to_string(),
+                ocr_type: OcrType::Entry,
+                region: None,
+            })
+            .collect()
+    }
+
+    fn word(line_num: i32, left: i32, top: i32, text: &str) -> Data {
+        Data {
+            level: 5,
+            page_num: 1,
+            block_num: 1,
+            par_num: 1,
+            line_num,
+            word_num: 1,
+            left,
+            top,
+            width: 40,
+            height: 20,
+            conf: 90.0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn lines_with_regions() {
+        let mut line = word(1, 0, 0, "");
+        line.level = 4;
+        let lines = group_lines(&[
+            line,
+            word(1, 10, 12, "Butter"),
+            word(1, 200, 10, "1,99"),
+            word(2, 10, 40, " "),
+            word(2, 10, 40, "Milch"),
+            word(2, 200, 42, "0,99"),
+        ]);
+        assert_eq!(
+            vec![
+                (
+                    "Butter 1,99".to_string(),
+                    Region {
+                        left: 10,
+                        top: 10,
+                        width: 230,
+                        height: 22
+                    }
+                ),
+                (
+                    "Milch 0,99".to_string(),
+                    Region {
+                        left: 10,
+                        top: 40,
+                        width: 230,
+                        height: 22
+                    }
+                ),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("dairy");
+        database.create_product(1, "Butter");
+        database.create_product(1, "Milch");
+        database.create_product(1, "Joghurt");
+        database.create_bon("2024-12-24", 3.47);
+        database.create_entry(1, 1, 1.99);
+        database.create_entry(1, 2, 0.99);
+        database.create_entry(1, 3, 0.49);
+        let bons = database.get_bons();
+
+        let entry = |product: &str, price: f64| database::Entry {
+            category: "dairy".to_string(),
+            color: None,
+            product: product.to_string(),
+            price,
+        };
+        let mut draft = BonDraft {
+            date: "24.12.2024".to_string(),
+            items: vec![
+                entry("Buttr", 1.99),
+                entry("Milch", 0.99),
+                entry("Joghurt", 0.49),
+            ],
+            price: 3.47,
+            store: String::new(),
+        };
+        assert_eq!(1, draft.find_duplicate(&bons).expect("No duplicate").bon_id);
+
+        // one entry misread is still the same bon
+        draft.items[2] = entry("Jgrt Natur", 0.94);
+        assert!(draft.find_duplicate(&bons).is_some());
+
+        draft.items[1] = entry("Sahne", 0.99);
+        assert!(draft.find_duplicate(&bons).is_none());
+
+        draft.items[1] = entry("Milch", 0.99);
+        draft.date = "23.12.2024".to_string();
+        assert!(draft.find_duplicate(&bons).is_none());
+
+        draft.date = "24.12.2024".to_string();
+        draft.price = 3.48;
+        assert!(draft.find_duplicate(&bons).is_none());
+    }
+
+    #[test]
+    fn post_import() {
+        let root = std::env::temp_dir().join(format!("bon-scanner-archive-{}", std::process::id()));
+        let inbox = root.join("inbox");
+        let archive_path = root.join("archive");
+        fs::create_dir_all(&inbox).expect("Couldn't create directory");
+        let database = Database::new(":memory:");
+        database.create_database();
+        let draft = BonDraft {
+            date: "24.12.2024".to_string(),
+            store: "Corner Shop".to_string(),
+            ..Default::default()
+        };
+        let archive = |name: &str, action: PostImport| {
+            let file = inbox.join(name);
+            fs::write(&file, name).expect("Couldn't write file");
+            let file = file.to_string_lossy().into_owned();
+            let bon_id = import(&database, &draft, &file);
+            let target = archive(
+                &database,
+                bon_id,
+                &draft,
+                &file,
+                action,
+                &archive_path.to_string_lossy(),
+            )
+            .expect("Couldn't archive file");
+            (file, target)
+        };
+
+        let (file, target) = archive("IMG_1.jpg", PostImport::Keep);
+        assert_eq!(Some(PathBuf::from(&file)), target);
+        assert!(Path::new(&file).exists());
+
+        let (file, target) = archive("IMG_2.jpg", PostImport::Move);
+        assert_eq!(Some(archive_path.join("IMG_2.jpg")), target);
+        assert!(!Path::new(&file).exists());
+        assert_eq!(
+            "IMG_2.jpg",
+            fs::read_to_string(archive_path.join("IMG_2.jpg")).expect("Couldn't read file")
+        );
+
+        let (_, target) = archive("IMG_3.jpg", PostImport::Rename);
+        assert_eq!(Some(inbox.join("2024-12-24_Corner_Shop.jpg")), target);
+        let (_, target) = archive("IMG_4.jpg", PostImport::Rename);
+        assert_eq!(Some(inbox.join("2024-12-24_Corner_Shop_2.jpg")), target);
+
+        let (file, target) = archive("IMG_5.jpg", PostImport::Delete);
+        assert_eq!(None, target);
+        assert!(!Path::new(&file).exists());
+
+        let archived: Vec<Option<String>> = database
+            .get_processed_files()
+            .into_iter()
+            .map(|processed| processed.archive_path)
+            .collect();
+        assert_eq!(None, archived[0]);
+        assert_eq!(
+            Some(processed_path(&archive_path.join("IMG_2.jpg"))),
+            archived[1]
+        );
+        assert_eq!(
+            Some(processed_path(&inbox.join("2024-12-24_Corner_Shop.jpg"))),
+            archived[2]
+        );
+        assert_eq!(None, archived[4]);
+
+        // neither the kept nor the renamed bons show up for import again
+        fs::write(inbox.join("IMG_6.jpg"), "IMG_6.jpg").expect("Couldn't write file");
+        let processed = database.get_processed_files();
+        let listed: Vec<String> =
+            discovery::Discovery::new(&[inbox.to_string_lossy().into_owned()], false, &[], &[])
+                .expect("Couldn't build discovery")
+                .files()
+                .into_iter()
+                .filter(|file| !is_processed(&file.path, &processed))
+                .map(|file| file.name)
+                .collect();
+        assert_eq!(vec!["IMG_6.jpg"], listed);
+        assert!(is_processed(&archive_path.join("IMG_2.jpg"), &processed));
+
+        fs::remove_dir_all(&root).expect("Couldn't remove test directory");
+    }
+
+    #[test]
+    fn import_quotes() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        let draft = BonDraft {
+            date: "24.12.2024".to_string(),
+            items: vec![database::Entry::new("kids' stuff", "Children's Book", 9.99)],
+            price: 9.99,
+            store: "Trader Joe's".to_string(),
+        };
+        let bon_id = import(&database, &draft, "");
+        let bons = database.get_bons();
+        assert_eq!(bon_id, bons[0].bon_id);
+        assert_eq!("Trader Joe's", bons[0].store);
+        assert_eq!("Children's Book", bons[0].entries[0].product);
+        assert_eq!("kids' stuff", bons[0].entries[0].category);
+    }
+
+    #[test]
+    fn manual_entries() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("dairy");
+        database.create_product(1, "Butter");
+        database.create_product(1, "Buttermilch");
+        database.create_product(1, "Erdnussbutter");
+        database.create_product(1, "Milch");
+
+        let products = database.get_products();
+        assert_eq!(
+            vec!["Butter", "Buttermilch", "Erdnussbutter"],
+            complete_product(&products, "butt")
+        );
+        assert_eq!(
+            vec!["Milch", "Buttermilch"],
+            complete_product(&products, "MILCH")
+        );
+        assert!(complete_product(&products, " ").is_empty());
+
+        let entry = manual_entry(&database, " butter ", 1.99);
+        assert_eq!("Butter", entry.product);
+        assert_eq!("dairy", entry.category);
+        assert_eq!(1.99, entry.price);
+
+        let entry = manual_entry(&database, "Honig", 0.0);
+        assert_eq!("Honig", entry.product);
+        assert_eq!("", entry.category);
+    }
+
+    #[test]
+    fn merge_and_split() {
+        let mut lines = entries(&["Butter", "1,99", "Milch 0,99", "SUMME 2,98"]);
+        lines[0].region = Some(Region {
+            left: 10,
+            top: 10,
+            width: 100,
+            height: 20,
+        });
+        lines[1].region = Some(Region {
+            left: 200,
+            top: 12,
+            width: 40,
+            height: 20,
+        });
+        lines[3].ocr_type = OcrType::Sum;
+
+        assert!(merge_lines(&mut lines, 0));
+        assert_eq!("Butter 1,99", lines[0].name);
+        assert_eq!(
+            Some(Region {
+                left: 10,
+                top: 10,
+                width: 230,
+                height: 22
+            }),
+            lines[0].region
+        );
+        assert_eq!(3, lines.len());
+
+        // a marked line keeps its mark
+        assert!(merge_lines(&mut lines, 1));
+        assert_eq!("Milch 0,99 SUMME 2,98", lines[1].name);
+        assert!(matches!(lines[1].ocr_type, OcrType::Sum));
+        assert!(!merge_lines(&mut lines, 1));
+
+        assert!(split_line(&mut lines, 1, "Milch 0,99 SUMME 2,98", 10));
+        assert_eq!("Milch 0,99", lines[1].name);
+        assert_eq!("SUMME 2,98", lines[2].name);
+        assert!(matches!(lines[2].ocr_type, OcrType::Entry));
+
+        // splitting needs text on both sides
+        assert!(!split_line(&mut lines, 0, "Butter 1,99", 0));
+        assert!(!split_line(&mut lines, 0, "Butter 1,99", 11));
+        assert!(split_line(&mut lines, 0, "Bütter 1,99", 6));
+        assert_eq!("Bütter", lines[0].name);
+        assert_eq!(lines[0].region, lines[1].region);
+        assert_eq!(4, lines.len());
+    }
+
+    #[test]
+    fn detect_lines() {
+        let mut lines = entries(&[
+            "Butter 1,99",
+            "Milch 0,99",
+            "SUMME EUR 2,98",
+            "24.12.2024 12:00",
+        ]);
+        detect(&mut lines);
+        assert!(matches!(lines[0].ocr_type, OcrType::Entry));
+        assert!(matches!(lines[2].ocr_type, OcrType::Sum));
+        assert!(matches!(lines[3].ocr_type, OcrType::Date));
+
+        // lines marked by the user are kept
+        let mut lines = entries(&["01.12.2024", "24.12.2024", "Total 3,00"]);
+        lines[1].ocr_type = OcrType::Date;
+        detect(&mut lines);
+        assert!(matches!(lines[0].ocr_type, OcrType::Entry));
+        assert!(matches!(lines[1].ocr_type, OcrType::Date));
+        assert!(matches!(lines[2].ocr_type, OcrType::Sum));
+    }
+
+    #[test]
+    fn convert_and_import() {
+        let database = Database::new(":memory:");
+        database.create_database();
+        database.create_category("dairy");
+        database.create_category("bakery");
+        database.create_product(2, "Bread");
+        database.create_product(1, "Butter");
+
+        let mut lines = entries(&[
+            "Butter 1,99",
+            "Brot 2,49",
+            "Apples 3,00",
+            "SUMME 7,48",
+            "24,12,2024",
+        ]);
+        detect(&mut lines);
+        let draft = convert(&database, &lines);
+        assert_eq!("24.12.2024", draft.date);
+        assert_eq!(7.48, draft.price);
+        assert!(draft.price_matches());
+        let items: Vec<(&str, &str, f64)> = draft
+            .items
+            .iter()
+            .map(|item| (item.product.as_str(), item.category.as_str(), item.price))
+            .collect();
+        assert_eq!(
+            vec![
+                ("Butter", "dairy", 1.99),
+                ("Bread", "bakery", 2.49),
+                ("Apples", "", 3.0)
+            ],
+            items
+        );
+
+        let bon_id = import(&database, &draft, "/tmp/bon.jpg");
+        let bons = database.get_bons();
+        assert_eq!(bon_id, bons[0].bon_id);
+        assert_eq!("2024-12-24", bons[0].date);
+        assert_eq!(3, bons[0].entries.len());
+        assert_eq!("bakery", bons[0].entries[1].category);
+        assert_eq!(3, database.get_products().len());
+        assert_eq!(vec!["bon.jpg".to_string()], database.get_processed());
+        let processed = database.get_processed_files();
+        assert_eq!("/tmp/bon.jpg", processed[0].path);
+        assert_eq!(Some(bon_id), processed[0].bon_id);
+    }
+}
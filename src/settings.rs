@@ -1,26 +1,167 @@
+use crate::bank::StatementFormat;
+use crate::discovery::Discovery;
+use crate::scan::PostImport;
 use config::Config;
 use std::path::Path;
 
+const DEFAULT_FUNDING_ACCOUNT: &str = "assets:cash";
+const DEFAULT_PRICE_THRESHOLD: f64 = 10.0;
+
 pub struct Settings {
+    pub archive_path: String,
+    pub background_ocr: bool,
+    pub export_path: String,
+    pub funding_account: String,
+    pub import_exclude: Vec<String>,
+    pub import_include: Vec<String>,
     pub import_path: String,
+    pub import_paths: Vec<String>,
+    pub import_recursive: bool,
+    pub post_import: PostImport,
     pub settings_file: String,
     pub database_file: String,
+    pub price_threshold: f64,
+    pub statement_format: StatementFormat,
 }
 
 impl Default for Settings {
     fn default() -> Self {
+        Self::from_file(&Self::build_default_settings_path())
+    }
+}
+
+impl Settings {
+    // archived bons are moved next to the import folder by default
+    pub fn archive_path(&self) -> String {
+        let mut ret = Path::new(&self.import_path())
+            .join("archive")
+            .to_str()
+            .expect("Couldn't convert path to string")
+            .to_string();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(archive_path) = settings.get_string("archive_path") {
+                ret = archive_path;
+            }
+        }
+        ret
+    }
+
+    pub fn background_ocr(&self) -> bool {
+        let mut ret = false;
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(background_ocr) = settings.get_bool("background_ocr") {
+                ret = background_ocr;
+            }
+        }
+        ret
+    }
+
+    pub fn discovery(&self) -> color_eyre::Result<Discovery> {
+        Discovery::new(
+            &self.import_paths,
+            self.import_recursive,
+            &self.import_include,
+            &self.import_exclude,
+        )
+    }
+
+    pub fn export_path(&self) -> String {
+        let mut ret = Self::build_default_export_path();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(export_path) = settings.get_string("export_path") {
+                ret = export_path;
+            }
+        }
+        ret
+    }
+
+    pub fn from_file(settings_file: &str) -> Self {
         let mut settings = Self {
+            archive_path: String::new(),
+            background_ocr: false,
+            export_path: Self::build_default_export_path(),
+            funding_account: DEFAULT_FUNDING_ACCOUNT.to_string(),
+            import_exclude: Vec::new(),
+            import_include: Vec::new(),
             import_path: Self::build_default_import_path(),
-            settings_file: Self::build_default_settings_path(),
+            import_paths: Vec::new(),
+            import_recursive: false,
+            post_import: PostImport::default(),
+            settings_file: settings_file.to_string(),
             database_file: "".to_string(),
+            price_threshold: DEFAULT_PRICE_THRESHOLD,
+            statement_format: StatementFormat::default(),
         };
+        settings.archive_path = settings.archive_path();
+        settings.background_ocr = settings.background_ocr();
+        settings.export_path = settings.export_path();
+        settings.funding_account = settings.funding_account();
+        settings.import_exclude = settings.import_exclude();
+        settings.import_include = settings.import_include();
         settings.import_path = settings.import_path();
+        settings.import_paths = settings.import_paths();
+        settings.import_recursive = settings.import_recursive();
         settings.database_file = settings.database_path();
+        settings.post_import = settings.post_import();
+        settings.price_threshold = settings.price_threshold();
+        settings.statement_format = settings.statement_format();
         settings
     }
-}
 
-impl Settings {
+    pub fn funding_account(&self) -> String {
+        let mut ret = DEFAULT_FUNDING_ACCOUNT.to_string();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(funding_account) = settings.get_string("funding_account") {
+                ret = funding_account;
+            }
+        }
+        ret
+    }
+
+    pub fn import_exclude(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(import_exclude) = settings.get::<Vec<String>>("import_exclude") {
+                ret = import_exclude;
+            }
+        }
+        ret
+    }
+
+    pub fn import_include(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(import_include) = settings.get::<Vec<String>>("import_include") {
+                ret = import_include;
+            }
+        }
+        ret
+    }
+
     pub fn import_path(&self) -> String {
         let mut ret = Self::build_default_import_path();
         if self.settings_exists() {
@@ -35,11 +176,44 @@ impl Settings {
         ret
     }
 
+    // import_path stays the first root, further roots come from import_paths
+    pub fn import_paths(&self) -> Vec<String> {
+        let mut ret = vec![self.import_path()];
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(import_paths) = settings.get::<Vec<String>>("import_paths") {
+                for path in import_paths {
+                    if !ret.contains(&path) {
+                        ret.push(path);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
     pub fn import_path_exists(&self) -> bool {
         let import_path = Path::new(&self.import_path);
         import_path.exists()
     }
 
+    pub fn import_recursive(&self) -> bool {
+        let mut ret = false;
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(import_recursive) = settings.get_bool("import_recursive") {
+                ret = import_recursive;
+            }
+        }
+        ret
+    }
+
     fn build_default_import_path() -> String {
         let import_dir = dirs::picture_dir().expect("Couldn't detect picture folder");
         let import_path = Path::new(&import_dir);
@@ -49,6 +223,15 @@ impl Settings {
             .to_string()
     }
 
+    fn build_default_export_path() -> String {
+        let home = dirs::home_dir().expect("Couldn't detect home folder");
+        let home_dir = Path::new(&home);
+        home_dir
+            .to_str()
+            .expect("Couldn't convert path to string")
+            .to_string()
+    }
+
     fn build_default_database_path() -> String {
         let home = dirs::home_dir().expect("Couldn't detect home folder");
         let home_dir = Path::new(&home);
@@ -92,6 +275,64 @@ impl Settings {
         Self::default()
     }
 
+    pub fn post_import(&self) -> PostImport {
+        let mut ret = PostImport::default();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(post_import) = settings.get_string("post_import")
+                && let Some(post_import) = PostImport::parse(&post_import)
+            {
+                ret = post_import;
+            }
+        }
+        ret
+    }
+
+    pub fn price_threshold(&self) -> f64 {
+        let mut ret = DEFAULT_PRICE_THRESHOLD;
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(price_threshold) = settings.get_float("price_threshold") {
+                ret = price_threshold;
+            }
+        }
+        ret
+    }
+
+    pub fn statement_format(&self) -> StatementFormat {
+        let mut ret = StatementFormat::default();
+        if self.settings_exists() {
+            let settings = Config::builder()
+                .add_source(config::File::with_name(&self.settings_file))
+                .build()
+                .expect("Couldn't build settings file");
+            if let Ok(column) = settings.get_int("bank_amount_column") {
+                ret.amount_column = column as usize;
+            }
+            if let Ok(column) = settings.get_int("bank_date_column") {
+                ret.date_column = column as usize;
+            }
+            if let Ok(delimiter) = settings.get_string("bank_delimiter")
+                && let Some(delimiter) = delimiter.chars().next()
+            {
+                ret.delimiter = delimiter;
+            }
+            if let Ok(column) = settings.get_int("bank_description_column") {
+                ret.description_column = column as usize;
+            }
+            if let Ok(rows) = settings.get_int("bank_skip_rows") {
+                ret.skip_rows = rows as usize;
+            }
+        }
+        ret
+    }
+
     pub fn settings_exists(&self) -> bool {
         let settings = Path::new(&self.settings_file);
         settings.exists()
@@ -150,10 +391,34 @@ mod tests {
             .to_str()
             .expect("Couldn't build settings file")
             .to_string();
+        settings.archive_path = settings.archive_path();
+        settings.background_ocr = settings.background_ocr();
+        settings.export_path = settings.export_path();
+        settings.funding_account = settings.funding_account();
+        settings.import_exclude = settings.import_exclude();
+        settings.import_include = settings.import_include();
         settings.import_path = settings.import_path();
+        settings.import_paths = settings.import_paths();
+        settings.import_recursive = settings.import_recursive();
         settings.database_file = settings.database_path();
+        settings.post_import = settings.post_import();
+        settings.price_threshold = settings.price_threshold();
+        settings.statement_format = settings.statement_format();
+        assert_eq!(settings.archive_path, "config/archive");
+        assert!(settings.background_ocr);
+        assert_eq!(settings.export_path, "export");
+        assert_eq!(settings.funding_account, "assets:bank");
         assert_eq!(settings.import_path, "config");
+        assert_eq!(settings.import_paths, vec!["config", "testdata"]);
+        assert!(settings.import_recursive);
+        assert_eq!(settings.import_include, vec!["*.jpg", "*.png"]);
+        assert_eq!(settings.import_exclude, vec!["archive/**"]);
         assert_eq!(settings.database_file, "config/bon-scanner.sqlite");
+        assert_eq!(settings.post_import, PostImport::Move);
+        assert_eq!(settings.price_threshold, 15.0);
+        assert_eq!(settings.statement_format.amount_column, 3);
+        assert_eq!(settings.statement_format.date_column, 0);
+        assert_eq!(settings.statement_format.delimiter, ',');
     }
 
     #[test]
@@ -174,4 +439,12 @@ mod tests {
             .to_string();
         assert!(settings.database_exists())
     }
+
+    #[test]
+    fn from_file() {
+        let settings = Settings::from_file("config/bon-scanner.toml");
+        assert_eq!(settings.settings_file, "config/bon-scanner.toml");
+        assert_eq!(settings.database_file, "config/bon-scanner.sqlite");
+        assert_eq!(settings.funding_account, "assets:bank");
+    }
 }
@@ -1,23 +1,34 @@
+use image::{
+    RgbImage,
+    imageops::{self, FilterType},
+};
 use ratatui::{
     buffer::Buffer,
-    layout::{Alignment, Constraint, Flex, Layout, Rect},
-    style::{Modifier, Style, Stylize, palette::tailwind::CYAN, palette::tailwind::LIME},
-    text::Line,
+    layout::{Alignment, Constraint, Direction, Flex, Layout, Rect},
+    style::{
+        Color, Modifier, Style, Stylize, palette::tailwind::CYAN, palette::tailwind::LIME,
+        palette::tailwind::RED,
+    },
+    symbols::Marker,
+    text::{Line, Span},
     widgets::{
-        Block, BorderType, Clear, HighlightSpacing, List, ListItem, Paragraph, StatefulWidget,
-        Widget,
+        Axis, Bar, BarChart, BarGroup, Block, BorderType, Chart, Clear, Dataset, GraphType,
+        HighlightSpacing, LineGauge, List, ListItem, Paragraph, Sparkline, StatefulWidget, Widget,
     },
 };
 use std::path::Path;
+use std::str::FromStr;
 
 use crate::{
-    app::{App, AppState, OcrEntry, OcrType, SummaryEntry},
+    app::{App, AppState, BatchEntry, ChartView, ReconcileFocus, SummaryEntry},
     database,
+    scan::{OcrEntry, OcrType, Region},
 };
 
 const SELECTED_STYLE: Style = Style::new().bg(CYAN.c600).add_modifier(Modifier::BOLD);
 const FOOTER_STYLE: Style = Style::new().fg(CYAN.c600);
 const OKAY_STYLE: Style = Style::new().fg(LIME.c400);
+const ALERT_STYLE: Style = Style::new().fg(RED.c400);
 
 impl Widget for &mut App<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
@@ -25,35 +36,97 @@ impl Widget for &mut App<'_> {
             Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
 
         match self.current_state {
+            AppState::AddEntry => {
+                self.render_convert(main_area, buf);
+                self.render_edit(main_area, buf, "Add entry".to_string());
+                self.render_completions(main_area, buf);
+            }
+            AppState::Batch => {
+                self.render_home(main_area, buf);
+                self.render_batch(main_area, buf);
+            }
             AppState::Blacklist => {
                 self.render_home(main_area, buf);
                 self.render_ocr(main_area, buf);
                 self.render_edit(main_area, buf, "Add to blacklist".to_string());
             }
+            AppState::BonImage => {
+                self.render_home(main_area, buf);
+                self.render_bon_image(main_area, buf);
+            }
+            AppState::Budgets => {
+                self.render_budgets(main_area, buf);
+            }
             AppState::Category => {
                 self.render_convert(main_area, buf);
-                self.render_category(main_area, buf);
+                self.render_category(main_area, buf, "Categories".to_string());
+            }
+            AppState::Charts => {
+                self.render_charts(main_area, buf);
             }
             AppState::ConvertBon => {
                 self.render_convert(main_area, buf);
             }
+            AppState::DeleteCategory => {
+                self.render_convert(main_area, buf);
+                self.render_category(main_area, buf, "Reassign products to".to_string());
+            }
+            AppState::DuplicateBon => {
+                self.render_convert(main_area, buf);
+                self.render_duplicate_bon(main_area, buf);
+            }
             AppState::EditBonPrice => {
                 self.render_convert(main_area, buf);
                 self.render_edit(main_area, buf, "Edit bon price".to_string());
             }
+            AppState::EditBudget => {
+                self.render_budgets(main_area, buf);
+                self.render_edit(main_area, buf, "Edit monthly limit".to_string());
+            }
             AppState::EditCategory => {
                 self.render_convert(main_area, buf);
-                self.render_category(main_area, buf);
+                self.render_category(main_area, buf, "Categories".to_string());
                 self.render_edit(main_area, buf, "Add category".to_string());
             }
+            AppState::EditCategoryColor => {
+                self.render_convert(main_area, buf);
+                self.render_category(main_area, buf, "Categories".to_string());
+                self.render_edit(main_area, buf, "Edit category colour".to_string());
+            }
+            AppState::EditDate => {
+                self.render_convert(main_area, buf);
+                self.render_edit(main_area, buf, "Edit date".to_string());
+            }
             AppState::EditName => {
                 self.render_convert(main_area, buf);
                 self.render_edit(main_area, buf, "Edit name".to_string());
             }
+            AppState::EditOcrLine => {
+                self.render_home(main_area, buf);
+                self.render_ocr(main_area, buf);
+                self.render_edit(main_area, buf, "Edit line".to_string());
+            }
             AppState::EditPrice => {
                 self.render_convert(main_area, buf);
                 self.render_edit(main_area, buf, "Edit price".to_string());
             }
+            AppState::EditRule => {
+                self.render_home(main_area, buf);
+                self.render_rules(main_area, buf);
+                let title = match &self.rule_list.status {
+                    Some(status) => format!("Add rule keyword or /regex/: {status}"),
+                    None => "Add rule keyword or /regex/".to_string(),
+                };
+                self.render_edit(main_area, buf, title);
+            }
+            AppState::EditStore => {
+                self.render_convert(main_area, buf);
+                self.render_edit(main_area, buf, "Edit store".to_string());
+            }
+            AppState::ExportCsv => {
+                self.render_home(main_area, buf);
+                self.render_edit(main_area, buf, "Export CSV (from..to category)".to_string());
+            }
             AppState::Home => {
                 self.render_home(main_area, buf);
             }
@@ -61,10 +134,59 @@ impl Widget for &mut App<'_> {
                 self.render_home(main_area, buf);
                 self.render_import(main_area, buf);
             }
+            AppState::ImportStatement => {
+                self.render_reconcile(main_area, buf);
+                self.render_edit(
+                    main_area,
+                    buf,
+                    "Import bank statement (CSV file)".to_string(),
+                );
+            }
+            AppState::InsertOcrLine => {
+                self.render_home(main_area, buf);
+                self.render_ocr(main_area, buf);
+                self.render_edit(main_area, buf, "Insert line".to_string());
+            }
+            AppState::MergeCategory => {
+                self.render_convert(main_area, buf);
+                self.render_category(main_area, buf, "Merge into".to_string());
+            }
+            AppState::MoveCategory => {
+                self.render_convert(main_area, buf);
+                self.render_category(main_area, buf, "Move under".to_string());
+            }
             AppState::OCR => {
                 self.render_home(main_area, buf);
                 self.render_ocr(main_area, buf);
             }
+            AppState::Reconcile => {
+                self.render_reconcile(main_area, buf);
+            }
+            AppState::RenameCategory => {
+                self.render_convert(main_area, buf);
+                self.render_category(main_area, buf, "Categories".to_string());
+                self.render_edit(main_area, buf, "Rename category".to_string());
+            }
+            AppState::PriceHistory => {
+                self.render_price_history(main_area, buf);
+            }
+            AppState::Reports => {
+                self.render_reports(main_area, buf);
+            }
+            AppState::RuleCategory => {
+                self.render_home(main_area, buf);
+                self.render_rules(main_area, buf);
+                self.render_category(main_area, buf, "Category for rule".to_string());
+            }
+            AppState::Rules => {
+                self.render_home(main_area, buf);
+                self.render_rules(main_area, buf);
+            }
+            AppState::SplitOcrLine => {
+                self.render_home(main_area, buf);
+                self.render_ocr(main_area, buf);
+                self.render_edit(main_area, buf, "Split line at cursor".to_string());
+            }
         }
 
         self.render_footer(footer_area, buf);
@@ -72,13 +194,149 @@ impl Widget for &mut App<'_> {
 }
 
 impl App<'_> {
-    fn render_category(&mut self, area: Rect, buf: &mut Buffer) {
-        let popup_area = popup_area(area, 50, 50);
+    fn render_batch(&mut self, area: Rect, buf: &mut Buffer) {
+        let batch_area = popup_area(area, 80, 80);
+
+        let ready = self
+            .batch_list
+            .items
+            .iter()
+            .filter(|entry| entry.is_ready())
+            .count();
+        let mut title = format!(
+            "Batch import: {} ready, {} to review",
+            ready,
+            self.batch_list.items.len() - ready
+        );
+        if !self.batch_list.pending.is_empty() {
+            title.push_str(&format!(", scanning {}", self.batch_list.pending.len()));
+        }
+
+        let block = Block::bordered()
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let items: Vec<ListItem> = self.batch_list.items.iter().map(ListItem::from).collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Widget::render(Clear, batch_area, buf);
+        StatefulWidget::render(list, batch_area, buf, &mut self.batch_list.state);
+    }
+
+    fn render_bon_image(&mut self, area: Rect, buf: &mut Buffer) {
+        let image_area = popup_area(area, 60, 90);
+
+        let file_name = Path::new(&self.bon_image.file)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let block = Block::bordered()
+            .title(format!("Bon image {file_name}"))
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        let inner_area = block.inner(image_area);
+
+        Widget::render(Clear, image_area, buf);
+        Widget::render(block, image_area, buf);
+
+        if let Some(status) = &self.bon_image.status {
+            Paragraph::new(status.as_str()).render(inner_area, buf);
+        } else if let Some(image) = &self.bon_image.image {
+            render_half_blocks(inner_area, buf, image, self.bon_image.original_size, None);
+        }
+    }
+
+    fn render_budgets(&mut self, area: Rect, buf: &mut Buffer) {
+        let [categories_area, gauges_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(2)]).areas(area);
+
+        // categories
         let categories_block = Block::bordered()
             .title("Categories")
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded);
 
+        let categories: Vec<ListItem> = self
+            .budget_list
+            .items
+            .iter()
+            .map(|budget| {
+                let limit = budget
+                    .limit
+                    .map(|limit| format!(" {:.2} €", limit))
+                    .unwrap_or_default();
+                ListItem::new(Line::from(format!(
+                    "{}{}{}",
+                    "  ".repeat(budget.depth),
+                    budget.category,
+                    limit
+                )))
+            })
+            .collect();
+
+        let categories_list = List::new(categories)
+            .block(categories_block)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(
+            categories_list,
+            categories_area,
+            buf,
+            &mut self.budget_list.state,
+        );
+
+        // gauges
+        let gauges_block = Block::bordered()
+            .title(format!("Spent in {}", self.budget_list.month))
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let gauges_inner = gauges_block.inner(gauges_area);
+        Widget::render(gauges_block, gauges_area, buf);
+
+        let budgets: Vec<&database::Budget> = self
+            .budget_list
+            .items
+            .iter()
+            .filter(|budget| budget.limit.is_some())
+            .collect();
+        let rows = Layout::vertical(vec![Constraint::Length(1); budgets.len()]).split(gauges_inner);
+
+        budgets.iter().zip(rows.iter()).for_each(|(budget, row)| {
+            let limit = budget.limit.unwrap_or_default();
+            let style = if budget.spent > limit {
+                ALERT_STYLE
+            } else {
+                OKAY_STYLE
+            };
+            let gauge = LineGauge::default()
+                .filled_style(style)
+                .label(format!(
+                    "{} {:.2} / {:.2} €",
+                    budget.category, budget.spent, limit
+                ))
+                .ratio((budget.spent / limit).clamp(0.0, 1.0));
+            Widget::render(gauge, *row, buf);
+        });
+    }
+
+    fn render_category(&mut self, area: Rect, buf: &mut Buffer, title: String) {
+        let popup_area = popup_area(area, 50, 50);
+        let mut categories_block = Block::bordered()
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        if let Some(status) = &self.category_list.status {
+            categories_block =
+                categories_block.title_bottom(Line::styled(status.as_str(), ALERT_STYLE));
+        }
+
         let categories: Vec<ListItem> = self
             .category_list
             .items
@@ -100,6 +358,187 @@ impl App<'_> {
         );
     }
 
+    fn render_charts(&mut self, area: Rect, buf: &mut Buffer) {
+        let range = format!("last {} months", self.chart_list.products.months);
+        match self.chart_list.view {
+            ChartView::Categories => {
+                let mut shares: Vec<(String, f64)> = Vec::new();
+                self.chart_list
+                    .reports_in_range()
+                    .iter()
+                    .flat_map(|report| report.categories.iter())
+                    .for_each(|(category, total)| {
+                        if let Some(share) = shares.iter_mut().find(|elem| &elem.0 == category) {
+                            share.1 += total;
+                        } else {
+                            shares.push((category.clone(), *total));
+                        }
+                    });
+                shares.sort_by(|a, b| b.1.total_cmp(&a.1));
+                let sum: f64 = shares.iter().map(|share| share.1).sum();
+                let bars: Vec<Bar> = shares
+                    .iter()
+                    .map(|(category, total)| {
+                        let percent = if sum > 0.0 { total / sum * 100.0 } else { 0.0 };
+                        let color = self
+                            .category_list
+                            .items
+                            .iter()
+                            .find(|elem| &elem.category == category)
+                            .and_then(|elem| elem.color.clone());
+                        Bar::default()
+                            .label(Line::from(category.as_str()))
+                            .value((percent * 10.0).round() as u64)
+                            .text_value(format!("{:.1} % ({:.2} €)", percent, total))
+                            .style(category_style(&color))
+                    })
+                    .collect();
+                let block = Block::bordered()
+                    .title(format!("Category share ({range})"))
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded);
+                let chart = BarChart::default()
+                    .block(block)
+                    .direction(Direction::Horizontal)
+                    .bar_width(1)
+                    .bar_gap(1)
+                    .data(BarGroup::default().bars(&bars));
+                Widget::render(chart, area, buf);
+            }
+            ChartView::Product => {
+                let [products_area, chart_area] =
+                    Layout::horizontal([Constraint::Fill(1), Constraint::Fill(2)]).areas(area);
+
+                let products_block = Block::bordered()
+                    .title("Products")
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded);
+                let products: Vec<ListItem> = self
+                    .chart_list
+                    .products
+                    .items
+                    .iter()
+                    .map(|product| ListItem::from(product.product.as_str()))
+                    .collect();
+                let products_list = List::new(products)
+                    .block(products_block)
+                    .highlight_style(SELECTED_STYLE)
+                    .highlight_spacing(HighlightSpacing::Always);
+                StatefulWidget::render(
+                    products_list,
+                    products_area,
+                    buf,
+                    &mut self.chart_list.products.state,
+                );
+
+                let history = self.chart_list.products.window();
+                let points: Vec<(f64, f64)> = history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, point)| (i as f64, point.price))
+                    .collect();
+                let max_price = points.iter().map(|point| point.1).fold(0.0, f64::max);
+                let x_labels = match (history.first(), history.last()) {
+                    (Some(first), Some(last)) => vec![
+                        first.date.chars().take(10).collect::<String>(),
+                        last.date.chars().take(10).collect::<String>(),
+                    ],
+                    _ => Vec::new(),
+                };
+                let dataset = Dataset::default()
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(OKAY_STYLE)
+                    .data(&points);
+                let chart_block = Block::bordered()
+                    .title(format!("Price history ({range})"))
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded);
+                let chart = Chart::new(vec![dataset])
+                    .block(chart_block)
+                    .x_axis(
+                        Axis::default()
+                            .bounds([0.0, points.len().saturating_sub(1).max(1) as f64])
+                            .labels(x_labels),
+                    )
+                    .y_axis(
+                        Axis::default()
+                            .bounds([0.0, max_price * 1.1])
+                            .labels(vec!["0.00".to_string(), format!("{:.2}", max_price * 1.1)]),
+                    );
+                Widget::render(chart, chart_area, buf);
+            }
+            ChartView::Spending => {
+                let [sparkline_area, chart_area] =
+                    Layout::vertical([Constraint::Length(5), Constraint::Fill(1)]).areas(area);
+
+                let reports = self.chart_list.reports_in_range();
+                let totals: Vec<u64> = reports
+                    .iter()
+                    .map(|report| report.total.round() as u64)
+                    .collect();
+                let sparkline_block = Block::bordered()
+                    .title(format!("Trend ({range})"))
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded);
+                let sparkline = Sparkline::default()
+                    .block(sparkline_block)
+                    .data(&totals)
+                    .style(OKAY_STYLE);
+                Widget::render(sparkline, sparkline_area, buf);
+
+                let bars: Vec<Bar> = reports
+                    .iter()
+                    .map(|report| {
+                        Bar::default()
+                            .label(Line::from(report.period.as_str()))
+                            .value(report.total.round() as u64)
+                            .text_value(format!("{:.0}", report.total))
+                    })
+                    .collect();
+                let chart_block = Block::bordered()
+                    .title(format!("Spending per month ({range})"))
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded);
+                let chart = BarChart::default()
+                    .block(chart_block)
+                    .bar_width(7)
+                    .bar_gap(1)
+                    .bar_style(FOOTER_STYLE)
+                    .data(BarGroup::default().bars(&bars));
+                Widget::render(chart, chart_area, buf);
+            }
+        }
+    }
+
+    // known products matching the typed name, listed below the edit field
+    fn render_completions(&mut self, area: Rect, buf: &mut Buffer) {
+        if self.new_bon_list.completions.is_empty() {
+            return;
+        }
+        let popup_area = popup_area(area, 30, 50);
+        let vertical = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center);
+        let [edit_area] = vertical.areas(popup_area);
+        let height = (self.new_bon_list.completions.len() as u16 + 2)
+            .min(area.bottom().saturating_sub(edit_area.bottom()));
+        let completions_area = Rect::new(edit_area.x, edit_area.bottom(), edit_area.width, height);
+
+        let block = Block::bordered()
+            .title("Complete: Tab")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let completions: Vec<ListItem> = self
+            .new_bon_list
+            .completions
+            .iter()
+            .map(|product| ListItem::new(product.as_str()))
+            .collect();
+
+        Widget::render(Clear, completions_area, buf);
+        Widget::render(List::new(completions).block(block), completions_area, buf);
+    }
+
     fn render_convert(&mut self, area: Rect, buf: &mut Buffer) {
         let [items_area, details_area] =
             Layout::horizontal([Constraint::Fill(2), Constraint::Fill(1)]).areas(area);
@@ -130,9 +569,29 @@ impl App<'_> {
 
         let details_line = if let Some(i) = self.new_bon_list.state.selected() {
             let entry = &self.new_bon_list.items[i];
+            let suggestion = if entry.category.is_empty() {
+                self.classifier
+                    .suggest(&entry.product)
+                    .filter(|suggestion| {
+                        self.category_list
+                            .items
+                            .iter()
+                            .any(|category| category.category == suggestion.category)
+                    })
+                    .map(|suggestion| {
+                        format!(
+                            "\nsuggestion: {} ({:.0} %)",
+                            suggestion.category,
+                            suggestion.confidence * 100.0
+                        )
+                    })
+                    .unwrap_or_default()
+            } else {
+                String::new()
+            };
             format!(
-                "product: {}\nprice: {} €\ncategory: {}",
-                entry.product, entry.price, entry.category
+                "product: {}\nprice: {} €\ncategory: {}{}",
+                entry.product, entry.price, entry.category, suggestion
             )
         } else {
             "".to_string()
@@ -157,7 +616,11 @@ impl App<'_> {
             .file_name()
             .unwrap_or_default()
             .to_string_lossy();
-        let file_line = Line::from(format!("file: {}\n", file_name));
+        let file_line = if self.ocr_file.is_empty() {
+            Line::from("file: none (manual entry)\n")
+        } else {
+            Line::from(format!("file: {}\n", file_name))
+        };
         let price_ocr_line = Line::styled(
             format!("price (OCR): {} €\n", self.new_bon_list.price_ocr),
             style,
@@ -170,13 +633,68 @@ impl App<'_> {
             style,
         );
         let date_line = Line::from(format!("date: {}", self.new_bon_list.date));
+        let store_line = Line::from(format!("store: {}", self.new_bon_list.store));
 
-        let summary_text = vec![file_line, price_ocr_line, price_calc_line, date_line];
+        let mut summary_text = vec![
+            file_line,
+            price_ocr_line,
+            price_calc_line,
+            date_line,
+            store_line,
+        ];
+        self.new_bon_list.warnings.iter().for_each(|warning| {
+            summary_text.push(Line::styled(
+                format!("over budget: {}", warning),
+                ALERT_STYLE,
+            ))
+        });
         let summary = Paragraph::new(summary_text).block(summary_block);
 
         Widget::render(summary, summary_area, buf);
     }
 
+    fn render_duplicate_bon(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(bon) = &self.duplicate_bon else {
+            return;
+        };
+        let duplicate_area = popup_area(area, 80, 60);
+
+        let block = Block::bordered()
+            .title("Possible duplicate")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        let inner_area = block.inner(duplicate_area);
+
+        Widget::render(Clear, duplicate_area, buf);
+        Widget::render(block, duplicate_area, buf);
+
+        let [new_area, existing_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(inner_area);
+
+        let new_bon = Paragraph::new(bon_lines(
+            &self.new_bon_list.date,
+            &self.new_bon_list.store,
+            self.new_bon_list.price_ocr,
+            &self.new_bon_list.items,
+        ))
+        .block(
+            Block::bordered()
+                .title("New bon")
+                .title_alignment(Alignment::Center)
+                .border_type(BorderType::Rounded),
+        );
+        Widget::render(new_bon, new_area, buf);
+
+        let existing_bon =
+            Paragraph::new(bon_lines(&bon.date, &bon.store, bon.price, &bon.entries)).block(
+                Block::bordered()
+                    .title(format!("Bon {}", bon.bon_id))
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded),
+            );
+        Widget::render(existing_bon, existing_area, buf);
+    }
+
     fn render_edit(&mut self, area: Rect, buf: &mut Buffer, msg: String) {
         let popup_area = popup_area(area, 30, 50);
         let vertical = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center);
@@ -195,15 +713,50 @@ impl App<'_> {
 
     fn render_footer(&self, area: Rect, buf: &mut Buffer) {
         let text = match self.current_state {
-            AppState::Category => "Add: a | Select: Enter | Close: Esc | Quit: q",
+            AppState::AddEntry => "Add: Enter | Complete: Tab | Close: Esc",
+            AppState::BonImage => "Open in Image Viewer: o | Close: Esc | Quit: q",
+            AppState::Budgets => {
+                "Next: j | Previous: k | Set Limit: Enter | Remove Limit: x | Close: Esc | Quit: q"
+            }
+            AppState::Category => {
+                "Add: a | Rename: r | Merge: m | Move: u | Delete: x | Colour: l | Select: Enter | Close: Esc | Quit: q"
+            }
             AppState::ConvertBon => {
-                "Edit Category: c | Edit Name: n | Edit Price: p | Delete Entry: x | Edit Bon Price: o | Close: Esc | Quit: q"
+                "Add Entry: a | Duplicate Entry: D | Move Entry: J/K | Edit Category: c | Accept Suggestion: y | Edit Name: n | Edit Price: p | Delete Entry: x | Edit Bon Price: o | Edit Date: d | Edit Store: e | Undo: u | Redo: Ctrl-r | Close: Esc | Quit: q"
+            }
+            AppState::Charts => {
+                "Next View: t | Range: +/- | Next Product: j | Previous Product: k | Close: Esc | Quit: q"
+            }
+            AppState::DeleteCategory
+            | AppState::MergeCategory
+            | AppState::MoveCategory
+            | AppState::RuleCategory => {
+                "Next: j | Previous: k | Select: Enter | Close: Esc | Quit: q"
+            }
+            AppState::DuplicateBon => "Import Anyway: Enter | Cancel: Esc",
+            AppState::Home => {
+                "Next: j | Previous: k | Import: i | New Bon: n | Image: v | Hide: h | Charts: g | Prices: p | Budgets: b | Bank: a | Export CSV: e | Reports: m | Rules: r | Summary Level: +/- | Quit: q"
+            }
+            AppState::Batch => {
+                "Next: j | Previous: k | Approve: y | Approve All Matching: a | Review: Enter | Close: Esc | Quit: q"
+            }
+            AppState::Import => {
+                "Next: j | Previous: k | Process: Enter | Batch Import: b | Close: Esc | Quit: q"
             }
-            AppState::Home => "Next: j | Previous: k | Import: i | Hide: h | Quit: q",
-            AppState::Import => "Next: j | Previous: k | Process: Enter | Close: Esc | Quit: q",
             AppState::OCR => {
-                "Blacklist Entry: b  | Delete Entry: x | Import Bon: Enter | Mark Date: d | Mark Sum: s | Close: Esc | Quit: q"
+                "Blacklist Entry: b  | Delete Entry: x | Edit Line: e | Insert Line: a | Merge With Next: m | Split Line: w | Import Bon: Enter | Mark Date: d | Mark Sum: s | Undo: u | Redo: Ctrl-r | Close: Esc | Quit: q"
             }
+            AppState::PriceHistory => "Next: j | Previous: k | Window: +/- | Close: Esc | Quit: q",
+            AppState::Reconcile => {
+                "Next: j | Previous: k | Switch List: Tab | Pair: Enter | Unpair: x | Auto-pair: a | Import Statement: i | Close: Esc | Quit: q"
+            }
+            AppState::Reports => {
+                "Next: j | Previous: k | Toggle Month/Year: t | Close: Esc | Quit: q"
+            }
+            AppState::Rules => {
+                "Add: a | Delete: x | Toggle Regex: t | Priority: +/- | Re-apply to History: r | Close: Esc | Quit: q"
+            }
+            AppState::SplitOcrLine => "Split at Cursor: Enter | Close: Esc",
             // use the default for the editing windows
             _ => "Add: Enter | Close: Esc",
         };
@@ -218,10 +771,13 @@ impl App<'_> {
             Layout::vertical([Constraint::Fill(2), Constraint::Fill(1)]).areas(details_area);
 
         // bons
-        let bons_block = Block::bordered()
+        let mut bons_block = Block::bordered()
             .title("Bons")
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded);
+        if let Some(status) = &self.bon_list.status {
+            bons_block = bons_block.title_bottom(Line::styled(status.as_str(), ALERT_STYLE));
+        }
 
         let bons: Vec<ListItem> = self.bon_list.items.iter().map(ListItem::from).collect();
 
@@ -253,8 +809,24 @@ impl App<'_> {
         Widget::render(details_list, details_area, buf);
 
         // summary
+        let max_level = self
+            .category_list
+            .items
+            .iter()
+            .map(|category| category.depth)
+            .max()
+            .unwrap_or(0);
+        let summary_title = if max_level > 0 {
+            format!(
+                "Summary (level {}/{})",
+                self.summary_level.min(max_level),
+                max_level
+            )
+        } else {
+            "Summary".to_string()
+        };
         let summary_block = Block::bordered()
-            .title("Summary")
+            .title(summary_title)
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded);
 
@@ -268,16 +840,41 @@ impl App<'_> {
     fn render_import(&mut self, area: Rect, buf: &mut Buffer) {
         let import_area = popup_area(area, 50, 50);
 
-        let block = Block::bordered()
+        let mut block = Block::bordered()
             .title("Files")
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded);
+        if let Some((file, warning)) = &self.import_list.warning
+            && let Some(i) = self.import_list.state.selected()
+            && self
+                .import_list
+                .items
+                .get(i)
+                .is_some_and(|elem| elem.path == *file)
+        {
+            block = block.title_bottom(
+                Line::styled(format!(" {warning}, Enter imports it anyway "), ALERT_STYLE)
+                    .centered(),
+            );
+        }
 
         let items: Vec<ListItem> = self
             .import_list
             .items
             .iter()
-            .map(|elem| ListItem::from(elem.as_str()))
+            .map(|elem| {
+                let file = elem.path.to_string_lossy();
+                if self.ocr_cache.contains_key(file.as_ref()) {
+                    ListItem::from(Line::from(vec![
+                        Span::from(elem.name.as_str()),
+                        Span::styled(" scanned", OKAY_STYLE),
+                    ]))
+                } else if self.ocr_scanning.iter().any(|scanning| *scanning == file) {
+                    ListItem::from(format!("{} scanning..", elem.name))
+                } else {
+                    ListItem::from(elem.name.as_str())
+                }
+            })
             .collect();
 
         let list = List::new(items)
@@ -292,10 +889,19 @@ impl App<'_> {
     fn render_ocr(&mut self, area: Rect, buf: &mut Buffer) {
         let ocr_area = popup_area(area, 80, 80);
 
-        let block = Block::bordered()
+        let [lines_area, image_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(ocr_area);
+
+        Widget::render(Clear, ocr_area, buf);
+
+        // lines
+        let mut block = Block::bordered()
             .title("OCR")
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded);
+        if let Some(status) = &self.ocr_list.status {
+            block = block.title_bottom(Line::styled(status.as_str(), ALERT_STYLE));
+        }
 
         let items: Vec<ListItem> = self.ocr_list.items.iter().map(ListItem::from).collect();
 
@@ -304,8 +910,401 @@ impl App<'_> {
             .highlight_style(SELECTED_STYLE)
             .highlight_spacing(HighlightSpacing::Always);
 
-        Widget::render(Clear, ocr_area, buf);
-        StatefulWidget::render(list, ocr_area, buf, &mut self.ocr_list.state);
+        StatefulWidget::render(list, lines_area, buf, &mut self.ocr_list.state);
+
+        // image
+        let image_block = Block::bordered()
+            .title("Image")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        let inner_area = image_block.inner(image_area);
+
+        Widget::render(image_block, image_area, buf);
+
+        if let Some(status) = &self.bon_image.status {
+            Paragraph::new(status.as_str()).render(inner_area, buf);
+        } else if let Some(image) = &self.bon_image.image {
+            let region = self
+                .ocr_list
+                .state
+                .selected()
+                .and_then(|i| self.ocr_list.items.get(i))
+                .and_then(|entry| entry.region);
+            render_half_blocks(inner_area, buf, image, self.bon_image.original_size, region);
+        }
+    }
+
+    fn render_price_history(&mut self, area: Rect, buf: &mut Buffer) {
+        let [products_area, details_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(2)]).areas(area);
+
+        let [stats_area, purchases_area] =
+            Layout::vertical([Constraint::Length(8), Constraint::Fill(1)]).areas(details_area);
+
+        // products
+        let products_block = Block::bordered()
+            .title("Products")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let products: Vec<ListItem> = self
+            .price_list
+            .products
+            .items
+            .iter()
+            .map(|product| {
+                if self.price_list.alerts.contains(&product.product_id) {
+                    ListItem::new(Line::styled(format!("▲ {}", product.product), ALERT_STYLE))
+                } else {
+                    ListItem::new(Line::from(format!("  {}", product.product)))
+                }
+            })
+            .collect();
+
+        let products_list = List::new(products)
+            .block(products_block)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(
+            products_list,
+            products_area,
+            buf,
+            &mut self.price_list.products.state,
+        );
+
+        // stats
+        let window = self.price_list.products.window();
+        let stats_block = Block::bordered()
+            .title(format!(
+                "Statistics (last {} months)",
+                self.price_list.products.months
+            ))
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let stats_text = if let Some(stats) = database::PriceStats::new(window) {
+            let change_style = if stats.change > 0.0 {
+                ALERT_STYLE
+            } else {
+                OKAY_STYLE
+            };
+            vec![
+                Line::from(format!("last price: {:.2} €", stats.last)),
+                Line::from(format!("min: {:.2} €", stats.min)),
+                Line::from(format!("max: {:.2} €", stats.max)),
+                Line::from(format!("average: {:.2} €", stats.average)),
+                Line::styled(format!("change: {:+.1} %", stats.change), change_style),
+            ]
+        } else {
+            Vec::new()
+        };
+
+        let stats = Paragraph::new(stats_text).block(stats_block);
+
+        Widget::render(stats, stats_area, buf);
+
+        // purchases
+        let purchases_block = Block::bordered()
+            .title("Purchases")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let mut previous_price: Option<f64> = None;
+        let mut purchases: Vec<ListItem> = self
+            .price_list
+            .products
+            .history
+            .iter()
+            .map(|point| {
+                let change = previous_price
+                    .filter(|previous| *previous != 0.0)
+                    .map(|previous| {
+                        format!(" {:+.1} %", (point.price - previous) / previous * 100.0)
+                    })
+                    .unwrap_or_default();
+                previous_price = Some(point.price);
+                ListItem::new(Line::from(format!(
+                    "{} {} {:.2} €{}",
+                    point.date, point.store, point.price, change
+                )))
+            })
+            .collect();
+        purchases.reverse();
+
+        let purchases_list = List::new(purchases).block(purchases_block);
+
+        Widget::render(purchases_list, purchases_area, buf);
+    }
+
+    fn render_reconcile(&mut self, area: Rect, buf: &mut Buffer) {
+        let [bons_area, transactions_area, pairs_area] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+
+        let block = |title: &str, focused: bool| {
+            let block = Block::bordered()
+                .title(title.to_string())
+                .title_alignment(Alignment::Center)
+                .border_type(BorderType::Rounded);
+            if focused {
+                block.border_style(FOOTER_STYLE)
+            } else {
+                block
+            }
+        };
+        let focus = &self.reconcile_list.focus;
+
+        // bons
+        let bons: Vec<ListItem> = self
+            .reconcile_list
+            .bons
+            .iter()
+            .map(|bon| {
+                ListItem::new(Line::from(format!(
+                    "{} {:.2} € {}",
+                    bon.date, bon.price, bon.store
+                )))
+            })
+            .collect();
+
+        let bons_list = List::new(bons)
+            .block(block("Unmatched bons", *focus == ReconcileFocus::Bons))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(
+            bons_list,
+            bons_area,
+            buf,
+            &mut self.reconcile_list.bon_state,
+        );
+
+        // transactions
+        let transactions: Vec<ListItem> = self
+            .reconcile_list
+            .transactions
+            .iter()
+            .map(ListItem::from)
+            .collect();
+
+        let transactions_list = List::new(transactions)
+            .block(block(
+                "Unmatched transactions",
+                *focus == ReconcileFocus::Transactions,
+            ))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(
+            transactions_list,
+            transactions_area,
+            buf,
+            &mut self.reconcile_list.transaction_state,
+        );
+
+        // pairs
+        let title = match &self.reconcile_list.status {
+            Some(status) => format!("Pairs ({status})"),
+            None => "Pairs".to_string(),
+        };
+        let pairs: Vec<ListItem> = self
+            .reconcile_list
+            .pairs
+            .iter()
+            .map(|(bon, transaction)| {
+                ListItem::new(vec![
+                    Line::from(format!("{} {:.2} € {}", bon.date, bon.price, bon.store)),
+                    Line::styled(
+                        format!(
+                            "  {} {:.2} € {}",
+                            transaction.date, transaction.amount, transaction.description
+                        ),
+                        OKAY_STYLE,
+                    ),
+                ])
+            })
+            .collect();
+
+        let pairs_list = List::new(pairs)
+            .block(block(&title, *focus == ReconcileFocus::Pairs))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(
+            pairs_list,
+            pairs_area,
+            buf,
+            &mut self.reconcile_list.pair_state,
+        );
+    }
+
+    fn render_reports(&mut self, area: Rect, buf: &mut Buffer) {
+        let [periods_area, details_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+
+        // periods
+        let title = match self.report_list.period {
+            database::ReportPeriod::Month => "Months",
+            database::ReportPeriod::Year => "Years",
+        };
+        let periods_block = Block::bordered()
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let periods: Vec<ListItem> = self.report_list.items.iter().map(ListItem::from).collect();
+
+        let periods_list = List::new(periods)
+            .block(periods_block)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(periods_list, periods_area, buf, &mut self.report_list.state);
+
+        // details
+        let details_block = Block::bordered()
+            .title("Categories")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let details: Vec<ListItem> = if let Some(i) = self.report_list.state.selected() {
+            let report = &self.report_list.items[i];
+            let mut details: Vec<ListItem> = report
+                .categories
+                .iter()
+                .map(|(category, total)| {
+                    let color = self
+                        .category_list
+                        .items
+                        .iter()
+                        .find(|elem| &elem.category == category)
+                        .and_then(|elem| elem.color.clone());
+                    ListItem::new(Line::from(vec![
+                        Span::styled(category.to_string(), category_style(&color)),
+                        Span::from(format!(" {:.2} €", total)),
+                    ]))
+                })
+                .collect();
+            details.push(ListItem::new(
+                Line::from(format!("total {:.2} €", report.total)).add_modifier(Modifier::BOLD),
+            ));
+            details
+        } else {
+            Vec::new()
+        };
+
+        let details_list = List::new(details).block(details_block);
+
+        Widget::render(details_list, details_area, buf);
+    }
+
+    fn render_rules(&mut self, area: Rect, buf: &mut Buffer) {
+        let rules_area = popup_area(area, 80, 80);
+
+        let title = match self.rule_list.updated {
+            Some(updated) => format!("Rules ({updated} products categorised)"),
+            None => "Rules".to_string(),
+        };
+        let mut block = Block::bordered()
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        if let Some(status) = &self.rule_list.status {
+            block = block.title_bottom(Line::styled(status.as_str(), ALERT_STYLE));
+        }
+
+        let items: Vec<ListItem> = self.rule_list.items.iter().map(ListItem::from).collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Widget::render(Clear, rules_area, buf);
+        StatefulWidget::render(list, rules_area, buf, &mut self.rule_list.state);
+    }
+}
+
+fn bon_lines<'a>(
+    date: &str,
+    store: &str,
+    price: f64,
+    entries: &'a [database::Entry],
+) -> Vec<Line<'a>> {
+    let mut lines = vec![
+        Line::from(format!("date: {date}")),
+        Line::from(format!("store: {store}")),
+        Line::from(format!("price: {price:.2} €")),
+        Line::from(""),
+    ];
+    lines.extend(entries.iter().map(|entry| {
+        Line::from(vec![
+            Span::styled(entry.category.as_str(), category_style(&entry.color)),
+            Span::from(format!(" {} {} €", entry.product, entry.price)),
+        ])
+    }));
+    lines
+}
+
+fn category_style(color: &Option<String>) -> Style {
+    color
+        .as_deref()
+        .and_then(|color| Color::from_str(color).ok())
+        .map_or_else(Style::default, |color| Style::new().fg(color))
+}
+
+// every cell shows two pixels, the upper one as foreground of a half block and the lower one as
+// background
+fn render_half_blocks(
+    area: Rect,
+    buf: &mut Buffer,
+    image: &RgbImage,
+    original_size: (u32, u32),
+    highlight: Option<Region>,
+) {
+    if area.width == 0 || area.height == 0 || image.width() == 0 || image.height() == 0 {
+        return;
+    }
+    let scale = f64::min(
+        f64::from(area.width) / f64::from(image.width()),
+        f64::from(area.height * 2) / f64::from(image.height()),
+    );
+    let width = ((f64::from(image.width()) * scale) as u32).max(1);
+    let height = ((f64::from(image.height()) * scale) as u32).max(1);
+    let resized = imageops::resize(image, width, height, FilterType::Triangle);
+    // regions are measured on the original image
+    let region_scale = f64::from(width) / f64::from(original_size.0.max(1));
+    let highlighted = |x: u32, y: u32| {
+        highlight.is_some_and(|region| {
+            let (x, y) = (f64::from(x), f64::from(y));
+            x >= f64::from(region.left) * region_scale
+                && x < f64::from(region.left + region.width) * region_scale
+                && y >= f64::from(region.top) * region_scale
+                && y < f64::from(region.top + region.height) * region_scale
+        })
+    };
+    let color = |x: u32, y: u32| {
+        let [r, g, b] = resized.get_pixel(x, y).0;
+        if highlighted(x, y) {
+            Color::Rgb(r / 2 + 127, g / 2 + 100, b / 2)
+        } else {
+            Color::Rgb(r, g, b)
+        }
+    };
+    let x_offset = area.x + (area.width - width as u16) / 2;
+    let y_offset = area.y + (area.height - height.div_ceil(2) as u16) / 2;
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            let cell = &mut buf[(x_offset + x as u16, y_offset + (y / 2) as u16)];
+            cell.set_char('▀').set_fg(color(x, y));
+            if y + 1 < height {
+                cell.set_bg(color(x, y + 1));
+            }
+        }
     }
 }
 
@@ -317,26 +1316,66 @@ fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
     area
 }
 
+impl From<&BatchEntry> for ListItem<'_> {
+    fn from(value: &BatchEntry) -> Self {
+        let file = Path::new(&value.file)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let line = if let Some(error) = &value.error {
+            Line::styled(format!("{} failed: {}", file, error), ALERT_STYLE)
+        } else {
+            let (status, style) = if value.is_ready() {
+                ("ready", OKAY_STYLE)
+            } else {
+                ("review", ALERT_STYLE)
+            };
+            let mut spans = vec![
+                Span::styled(format!("{:<7}", status), style),
+                Span::from(format!(
+                    "{} {} {:.2} € ({:.2} €)",
+                    file,
+                    value.draft.date,
+                    value.draft.price,
+                    value.draft.items_total()
+                )),
+            ];
+            if let Some(duplicate) = &value.duplicate {
+                spans.push(Span::styled(format!(" {duplicate}"), ALERT_STYLE));
+            }
+            Line::from(spans)
+        };
+        ListItem::new(line)
+    }
+}
+
 impl From<&database::Bon> for ListItem<'_> {
     fn from(value: &database::Bon) -> Self {
-        let line = Line::from(format!("{} {} €", value.date, value.price));
+        let line = if value.store.is_empty() {
+            Line::from(format!("{} {} €", value.date, value.price))
+        } else {
+            Line::from(format!("{} {} {} €", value.date, value.store, value.price))
+        };
         ListItem::new(line)
     }
 }
 
 impl From<&database::Category> for ListItem<'_> {
     fn from(value: &database::Category) -> Self {
-        let line = Line::from(value.category.to_string());
+        let line = Line::styled(
+            format!("{}{}", "  ".repeat(value.depth), value.category),
+            category_style(&value.color),
+        );
         ListItem::new(line)
     }
 }
 
 impl From<&database::Entry> for ListItem<'_> {
     fn from(value: &database::Entry) -> Self {
-        let line = Line::from(format!(
-            "{} {} {} €",
-            value.category, value.product, value.price
-        ));
+        let line = Line::from(vec![
+            Span::styled(value.category.to_string(), category_style(&value.color)),
+            Span::from(format!(" {} {} €", value.product, value.price)),
+        ]);
         ListItem::new(line)
     }
 }
@@ -353,10 +1392,35 @@ impl From<&OcrEntry> for ListItem<'_> {
     }
 }
 
+impl From<&database::PeriodReport> for ListItem<'_> {
+    fn from(value: &database::PeriodReport) -> Self {
+        let change = value
+            .change
+            .map(|change| format!(" {:+.1} %", change))
+            .unwrap_or_default();
+        let line = Line::from(format!("{} {:.2} €{}", value.period, value.total, change));
+        ListItem::new(line)
+    }
+}
+
+impl From<&database::Rule> for ListItem<'_> {
+    fn from(value: &database::Rule) -> Self {
+        let kind = if value.is_regex { "regex" } else { "keyword" };
+        let line = Line::from(format!(
+            "{:>3} {} \"{}\" → {}",
+            value.priority, kind, value.pattern, value.category
+        ));
+        ListItem::new(line)
+    }
+}
+
 impl From<&SummaryEntry> for ListItem<'_> {
     fn from(value: &SummaryEntry) -> Self {
         let line = if value.category != "total" {
-            Line::from(format!("{} {:.2} €", value.category, value.total))
+            Line::from(vec![
+                Span::styled(value.category.to_string(), category_style(&value.color)),
+                Span::from(format!(" {:.2} €", value.total)),
+            ])
         } else {
             Line::from(format!("{} {:.2} €", value.category, value.total))
                 .add_modifier(Modifier::BOLD)
@@ -364,3 +1428,12 @@ impl From<&SummaryEntry> for ListItem<'_> {
         ListItem::new(line)
     }
 }
+
+impl From<&database::Transaction> for ListItem<'_> {
+    fn from(value: &database::Transaction) -> Self {
+        ListItem::new(Line::from(format!(
+            "{} {:.2} € {}",
+            value.date, value.amount, value.description
+        )))
+    }
+}